@@ -0,0 +1,446 @@
+//! Build script helper for [`eei_vfd`]
+//!
+//! Converting images on the MCU is expensive, so this crate does it at compile time.
+//! Images are loaded from PNG files, dithered down to one bit per pixel and packed
+//! into exactly the layout of [`Display256x50`] (or any other panel size) for the chosen
+//! rotation. The result is written as `static` byte arrays into `OUT_DIR`.
+//!
+//! ```no_run
+//! // build.rs
+//! use eei_vfd::graphics::DisplayRotation;
+//! use eei_vfd_build::{Dither, ImageAsset};
+//!
+//! fn main() {
+//!     eei_vfd_build::generate(
+//!         "vfd_assets.rs",
+//!         &[ImageAsset::new("LOGO", "assets/logo.png")
+//!             .dither(Dither::Atkinson)
+//!             .rotation(DisplayRotation::Rotate90)],
+//!     )
+//!     .unwrap();
+//! }
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! include!(concat!(env!("OUT_DIR"), "/vfd_assets.rs"));
+//!
+//! vfd.update_frame(&LOGO)?;
+//! ```
+//!
+//! [`Display256x50`]: eei_vfd::gp1287bi::Display256x50
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{env, fmt, fs, io};
+
+use eei_vfd::buffer_len;
+use eei_vfd::color::Color;
+use eei_vfd::gp1287bi;
+use eei_vfd::graphics::{Display, DisplayRotation, VarDisplay};
+use embedded_graphics_core::prelude::*;
+
+/// Algorithm used to reduce a grayscale image to one bit per pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    /// Pixels brighter than the given luma are lit, no error diffusion
+    Threshold(u8),
+    /// Floyd-Steinberg error diffusion, smooth gradients
+    FloydSteinberg,
+    /// Atkinson error diffusion, higher contrast and less noise on small panels
+    Atkinson,
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Dither::Threshold(0x80)
+    }
+}
+
+impl FromStr for Dither {
+    type Err = Error;
+
+    /// Parses `threshold`, `threshold:<luma>`, `floyd-steinberg` and `atkinson`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threshold" => Ok(Dither::default()),
+            "floyd-steinberg" | "floyd_steinberg" => Ok(Dither::FloydSteinberg),
+            "atkinson" => Ok(Dither::Atkinson),
+            _ => s
+                .strip_prefix("threshold:")
+                .and_then(|luma| luma.parse().ok())
+                .map(Dither::Threshold)
+                .ok_or_else(|| Error::UnknownDither(s.into())),
+        }
+    }
+}
+
+/// Errors while compiling an image asset
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the image or writing the generated file failed
+    Io(io::Error),
+    /// The image is not a valid PNG
+    Decode(png::DecodingError),
+    /// The image does not fit onto the panel in the chosen rotation
+    TooLarge {
+        /// Width of the image
+        width: u32,
+        /// Height of the image
+        height: u32,
+        /// Width available in the chosen rotation
+        max_width: u32,
+        /// Height available in the chosen rotation
+        max_height: u32,
+    },
+    /// The luma buffer does not match the given dimensions
+    BadLength,
+    /// The dither name could not be parsed
+    UnknownDither(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Decode(e) => write!(f, "png decoding error: {}", e),
+            Error::TooLarge {
+                width,
+                height,
+                max_width,
+                max_height,
+            } => write!(
+                f,
+                "image of {}x{} does not fit onto {}x{}",
+                width, height, max_width, max_height
+            ),
+            Error::BadLength => write!(f, "luma buffer does not match the image size"),
+            Error::UnknownDither(name) => write!(f, "unknown dither algorithm: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for Error {
+    fn from(e: png::DecodingError) -> Self {
+        Error::Decode(e)
+    }
+}
+
+/// An image which gets compiled into a `static` packed frame
+#[derive(Clone, Debug)]
+pub struct ImageAsset {
+    name: String,
+    path: PathBuf,
+    dither: Dither,
+    rotation: DisplayRotation,
+    invert: bool,
+    width: u32,
+    height: u32,
+}
+
+impl ImageAsset {
+    /// Creates an asset named `name` (the identifier of the generated static) from a PNG file
+    ///
+    /// Defaults to a [gp1287bi](eei_vfd::gp1287bi) panel without rotation and threshold dithering.
+    pub fn new(name: &str, path: impl AsRef<Path>) -> Self {
+        ImageAsset {
+            name: name.into(),
+            path: path.as_ref().into(),
+            dither: Dither::default(),
+            rotation: DisplayRotation::default(),
+            invert: false,
+            width: gp1287bi::WIDTH,
+            height: gp1287bi::HEIGHT,
+        }
+    }
+
+    /// Sets the dither algorithm
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Sets the rotation the frame will be packed for
+    pub fn rotation(mut self, rotation: DisplayRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Lights dark pixels instead of bright ones
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Sets the unrotated panel size, use this for displays other than the gp1287bi
+    pub fn panel(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Loads the image and returns the packed frame
+    pub fn pack(&self) -> Result<Vec<u8>, Error> {
+        let (width, height, luma) = load_luma(&self.path, self.invert)?;
+        pack_luma(
+            &luma,
+            width,
+            height,
+            self.dither,
+            self.rotation,
+            self.width,
+            self.height,
+        )
+    }
+
+    /// Renders the asset as a rust `static` item
+    pub fn to_rust(&self) -> Result<String, Error> {
+        let frame = self.pack()?;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "/// `{}` packed for a {}x{} panel, {:?}",
+            self.path.display(),
+            self.width,
+            self.height,
+            self.rotation
+        );
+        let _ = writeln!(out, "#[rustfmt::skip]");
+        let _ = writeln!(out, "pub static {}: [u8; {}] = [", self.name, frame.len());
+        for line in frame.chunks(16) {
+            out.push_str("   ");
+            for byte in line {
+                let _ = write!(out, " 0x{:02x},", byte);
+            }
+            out.push('\n');
+        }
+        out.push_str("];\n");
+        Ok(out)
+    }
+}
+
+/// Compiles all assets into `$OUT_DIR/<file>`
+///
+/// Meant to be called from a build script, it also tells cargo to rerun it if one of the images changes.
+pub fn generate(file: &str, assets: &[ImageAsset]) -> Result<(), Error> {
+    let out_dir = env::var_os("OUT_DIR")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+    let mut out = String::from("// Generated by eei_vfd_build, do not edit.\n\n");
+    for asset in assets {
+        println!("cargo:rerun-if-changed={}", asset.path.display());
+        out.push_str(&asset.to_rust()?);
+    }
+    fs::write(Path::new(&out_dir).join(file), out)?;
+    Ok(())
+}
+
+/// Dithers an 8 bit grayscale image and packs it for a panel of `panel_width` x `panel_height`
+///
+/// The image is placed in the top left corner of the rotated panel, the rest stays dark.
+pub fn pack_luma(
+    luma: &[u8],
+    width: u32,
+    height: u32,
+    dither: Dither,
+    rotation: DisplayRotation,
+    panel_width: u32,
+    panel_height: u32,
+) -> Result<Vec<u8>, Error> {
+    if luma.len() != width as usize * height as usize {
+        return Err(Error::BadLength);
+    }
+    let (max_width, max_height) = match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (panel_width, panel_height),
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => (panel_height, panel_width),
+    };
+    if width > max_width || height > max_height {
+        return Err(Error::TooLarge {
+            width,
+            height,
+            max_width,
+            max_height,
+        });
+    }
+
+    let bits = dither_luma(luma, width as usize, height as usize, dither);
+
+    let mut buffer =
+        vec![Color::Dark.get_byte_value(); buffer_len(panel_width as usize, panel_height as usize)];
+    let mut display = VarDisplay::new(panel_width, panel_height, &mut buffer);
+    display.set_rotation(rotation);
+    let pixels = bits.iter().enumerate().map(|(i, lit)| {
+        let point = Point::new((i % width as usize) as i32, (i / width as usize) as i32);
+        Pixel(point, if *lit { Color::Green } else { Color::Dark })
+    });
+    let _ = display.draw_iter(pixels);
+    Ok(buffer)
+}
+
+// reduces the image to one bit per pixel, true means lit
+fn dither_luma(luma: &[u8], width: usize, height: usize, dither: Dither) -> Vec<bool> {
+    let (threshold, spread): (i16, &[(isize, isize, i16)]) = match dither {
+        Dither::Threshold(threshold) => {
+            return luma.iter().map(|l| *l >= threshold).collect();
+        }
+        Dither::FloydSteinberg => (0x80, &[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)]),
+        Dither::Atkinson => (
+            0x80,
+            &[
+                (1, 0, 1),
+                (2, 0, 1),
+                (-1, 1, 1),
+                (0, 1, 1),
+                (1, 1, 1),
+                (0, 2, 1),
+            ],
+        ),
+    };
+    let divisor = match dither {
+        Dither::FloydSteinberg => 16,
+        _ => 8,
+    };
+
+    let mut levels: Vec<i16> = luma.iter().map(|l| *l as i16).collect();
+    let mut bits = vec![false; luma.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let old = levels[index];
+            let lit = old >= threshold;
+            bits[index] = lit;
+            let error = old - if lit { 0xff } else { 0x00 };
+            for (dx, dy, weight) in spread {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+                levels[ny as usize * width + nx as usize] += error * weight / divisor;
+            }
+        }
+    }
+    bits
+}
+
+// decodes a png into 8 bit luma, transparent pixels are composed onto black
+fn load_luma(path: &Path, invert: bool) -> Result<(u32, u32, Vec<u8>), Error> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut raw = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut raw)?;
+    let channels = info.color_type.samples();
+
+    let luma = raw[..info.buffer_size()]
+        .chunks(info.line_size)
+        .flat_map(|line| line[..info.width as usize * channels].chunks(channels))
+        .map(|px| {
+            let (value, alpha) = match px {
+                [l] => (*l as u32, 0xff),
+                [l, a] => (*l as u32, *a as u32),
+                [r, g, b] => (luma_of(*r, *g, *b), 0xff),
+                [r, g, b, a, ..] => (luma_of(*r, *g, *b), *a as u32),
+                _ => (0, 0),
+            };
+            compose(value, alpha, invert)
+        })
+        .collect();
+    Ok((info.width, info.height, luma))
+}
+
+// composes a pixel onto black, inverting it first so transparent parts stay dark
+fn compose(value: u32, alpha: u32, invert: bool) -> u8 {
+    let value = if invert { 0xff - value } else { value };
+    (value * alpha / 0xff) as u8
+}
+
+fn luma_of(r: u8, g: u8, b: u8) -> u32 {
+    (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dither() {
+        assert_eq!("atkinson".parse::<Dither>().unwrap(), Dither::Atkinson);
+        assert_eq!(
+            "threshold:32".parse::<Dither>().unwrap(),
+            Dither::Threshold(32)
+        );
+        assert!("bayer".parse::<Dither>().is_err());
+    }
+
+    #[test]
+    fn packs_like_display() {
+        // a single lit pixel in the top left corner of a 16x8 panel rotated by 90 degrees
+        let mut luma = vec![0u8; 8 * 16];
+        luma[0] = 0xff;
+        let frame = pack_luma(
+            &luma,
+            8,
+            16,
+            Dither::default(),
+            DisplayRotation::Rotate90,
+            16,
+            8,
+        )
+        .unwrap();
+
+        let mut expected = [0u8; 16];
+        let mut display = VarDisplay::new(16, 8, &mut expected);
+        display.set_rotation(DisplayRotation::Rotate90);
+        let _ = Pixel(Point::zero(), Color::Green).draw(&mut display);
+
+        assert_eq!(frame, expected);
+        assert_eq!(frame.iter().map(|b| b.count_ones()).sum::<u32>(), 1);
+    }
+
+    #[test]
+    fn invert_keeps_transparency_dark() {
+        assert_eq!(compose(0x00, 0x00, true), 0x00);
+        assert_eq!(compose(0x00, 0xff, true), 0xff);
+        assert_eq!(compose(0xff, 0xff, true), 0x00);
+        assert_eq!(compose(0x40, 0x80, false), 0x20);
+    }
+
+    #[test]
+    fn rejects_oversized() {
+        let luma = vec![0u8; 16 * 8];
+        assert!(matches!(
+            pack_luma(
+                &luma,
+                16,
+                8,
+                Dither::Atkinson,
+                DisplayRotation::Rotate0,
+                8,
+                16
+            ),
+            Err(Error::TooLarge { .. })
+        ));
+        // the size would wrap around in u32
+        assert!(matches!(
+            pack_luma(
+                &[],
+                0x1_0000,
+                0x1_0000,
+                Dither::default(),
+                DisplayRotation::Rotate0,
+                8,
+                16
+            ),
+            Err(Error::BadLength)
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+//! Draws arbitrary pixels onto a VarDisplay of arbitrary size and rotation
+//!
+//! Input: width, height, rotation, then 5 bytes per pixel (x: i16, y: i16, color)
+
+#![no_main]
+
+use eei_vfd::graphics::{Display, DisplayRotation, VarDisplay};
+use eei_vfd::prelude::*;
+use embedded_graphics::prelude::*;
+use libfuzzer_sys::fuzz_target;
+
+const ROTATIONS: [DisplayRotation; 4] = [
+    DisplayRotation::Rotate0,
+    DisplayRotation::Rotate90,
+    DisplayRotation::Rotate180,
+    DisplayRotation::Rotate270,
+];
+
+fuzz_target!(|data: &[u8]| {
+    let (header, pixels) = match data {
+        [w, h, r, rest @ ..] => ((*w as u32 + 1, *h as u32 + 1, ROTATIONS[*r as usize % 4]), rest),
+        _ => return,
+    };
+    let (width, height, rotation) = header;
+    let mut buffer = vec![0u8; eei_vfd::buffer_len(width as usize, height as usize)];
+    let mut display = VarDisplay::new(width, height, &mut buffer);
+    display.set_rotation(rotation);
+    let (w, h) = match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (width, height),
+        _ => (height, width),
+    };
+
+    for pixel in pixels.chunks_exact(5) {
+        let x = i16::from_le_bytes([pixel[0], pixel[1]]) as i32;
+        let y = i16::from_le_bytes([pixel[2], pixel[3]]) as i32;
+        let color = if pixel[4] & 1 == 1 { Color::Green } else { Color::Dark };
+        let before = display.buffer().to_vec();
+        Pixel(Point::new(x, y), color).draw(&mut display).unwrap();
+
+        let inside = x >= 0 && y >= 0 && (x as u32) < w && (y as u32) < h;
+        if inside {
+            assert_eq!(display.get_pixel(Point::new(x, y)), Some(color));
+        } else {
+            assert_eq!(display.buffer(), &before[..]);
+        }
+    }
+});
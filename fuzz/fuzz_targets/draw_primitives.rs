@@ -0,0 +1,58 @@
+//! Draws arbitrary primitives onto a VarDisplay of arbitrary size and rotation
+//!
+//! Input: width, height, rotation, then 9 bytes per primitive (kind, 4 x i16 coordinates)
+
+#![no_main]
+
+use eei_vfd::graphics::{Display, DisplayRotation, VarDisplay};
+use eei_vfd::prelude::*;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle, Rectangle};
+use libfuzzer_sys::fuzz_target;
+
+const ROTATIONS: [DisplayRotation; 4] = [
+    DisplayRotation::Rotate0,
+    DisplayRotation::Rotate90,
+    DisplayRotation::Rotate180,
+    DisplayRotation::Rotate270,
+];
+
+// coordinates are limited so the primitives stay cheap to rasterize
+fn coordinate(bytes: &[u8]) -> i32 {
+    i16::from_le_bytes([bytes[0], bytes[1]]) as i32 % 512
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (width, height, rotation, primitives) = match data {
+        [w, h, r, rest @ ..] => (*w as u32 + 1, *h as u32 + 1, ROTATIONS[*r as usize % 4], rest),
+        _ => return,
+    };
+    // fill everything, including the unused bits at the end of each row
+    let mut buffer = vec![0xffu8; eei_vfd::buffer_len(width as usize, height as usize)];
+    let mut display = VarDisplay::new(width, height, &mut buffer);
+    display.set_rotation(rotation);
+
+    for primitive in primitives.chunks_exact(9) {
+        let (a, b) = (
+            Point::new(coordinate(&primitive[1..]), coordinate(&primitive[3..])),
+            Point::new(coordinate(&primitive[5..]), coordinate(&primitive[7..])),
+        );
+        let stroke = PrimitiveStyle::with_stroke(Color::Dark, 1);
+        let _ = match primitive[0] % 3 {
+            0 => Line::new(a, b).into_styled(stroke).draw(&mut display),
+            1 => Rectangle::with_corners(a, b)
+                .into_styled(PrimitiveStyle::with_fill(Color::Dark))
+                .draw(&mut display),
+            _ => Circle::new(a, b.x.unsigned_abs() % 256).into_styled(stroke).draw(&mut display),
+        };
+    }
+
+    // pixels outside of the display must never reach the padding bits
+    let used = width % 8;
+    if used != 0 {
+        let row_bytes = (width as usize).div_ceil(8);
+        for row in display.buffer().chunks(row_bytes) {
+            assert_eq!(row[row_bytes - 1] | (0xff << (8 - used)), 0xff);
+        }
+    }
+});
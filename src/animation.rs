@@ -0,0 +1,378 @@
+//! Frame based animations
+//!
+//! An [Animation] steps through the frames of a [FrameSource], showing each one for its own
+//! duration. It can either block using the delay provider of the driver ([Animation::play])
+//! or be driven by a cooperative scheduler ([Animation::poll]).
+
+use embedded_hal::{delay::DelayNs, digital::*, spi::SpiDevice};
+
+use crate::codec::{FrameError, FrameStream};
+use crate::gp1287bi::VFD256x50;
+use crate::traits::EEIDisplay;
+
+#[cfg(feature = "graphics")]
+use crate::graphics::Display;
+
+/// How an animation continues after its last frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PlayMode {
+    /// Stop on the last frame
+    #[default]
+    OneShot,
+    /// Start over with the first frame
+    Loop,
+    /// Play backwards to the first frame, then forwards again
+    PingPong,
+}
+
+/// Anything that can put frame `index` onto the display
+pub trait FrameSource<SPI, RST, DELAY>
+where
+    SPI: SpiDevice,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Number of frames
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no frames
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Transmits frame `index` to the display
+    ///
+    /// Sources which decode their frames report failures as [FrameError::Codec].
+    fn show(
+        &mut self,
+        index: usize,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+    ) -> Result<(), FrameError<SPI::Error>>;
+}
+
+/// Packed frames, e.g. statics generated by `eei_vfd_build`
+impl<'a, SPI, RST, DELAY> FrameSource<SPI, RST, DELAY> for &'a [&'a [u8]]
+where
+    SPI: SpiDevice,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn len(&self) -> usize {
+        <[&[u8]]>::len(self)
+    }
+
+    fn show(
+        &mut self,
+        index: usize,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+    ) -> Result<(), FrameError<SPI::Error>> {
+        vfd.update_frame(self[index]).map_err(FrameError::Spi)
+    }
+}
+
+/// Frames of a compressed [FrameStream], decoded straight into the GRAM
+///
+/// `DEPTH` needs to be at least the key frame interval the stream was encoded with.
+pub struct CompressedFrames<'a, const DEPTH: usize>(pub FrameStream<'a>);
+
+impl<'a, const DEPTH: usize, SPI, RST, DELAY> FrameSource<SPI, RST, DELAY>
+    for CompressedFrames<'a, DEPTH>
+where
+    SPI: SpiDevice,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn show(
+        &mut self,
+        index: usize,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+    ) -> Result<(), FrameError<SPI::Error>> {
+        vfd.update_frame_compressed(self.0.decoder::<DEPTH>(index)?)
+    }
+}
+
+/// Frames rendered on demand into a [Display] by a closure
+#[cfg(feature = "graphics")]
+pub struct RenderedFrames<'d, D, F> {
+    display: &'d mut D,
+    len: usize,
+    render: F,
+}
+
+#[cfg(feature = "graphics")]
+impl<'d, D, F> RenderedFrames<'d, D, F>
+where
+    D: Display,
+    F: FnMut(usize, &mut D),
+{
+    /// `render` gets called with the frame index and draws it onto `display`
+    pub fn new(display: &'d mut D, len: usize, render: F) -> Self {
+        RenderedFrames {
+            display,
+            len,
+            render,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<'d, D, F, SPI, RST, DELAY> FrameSource<SPI, RST, DELAY> for RenderedFrames<'d, D, F>
+where
+    D: Display,
+    F: FnMut(usize, &mut D),
+    SPI: SpiDevice,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn show(
+        &mut self,
+        index: usize,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+    ) -> Result<(), FrameError<SPI::Error>> {
+        (self.render)(index, self.display);
+        vfd.update_frame(self.display.buffer())
+            .map_err(FrameError::Spi)
+    }
+}
+
+/// Plays the frames of a [FrameSource]
+///
+/// Frame `i` is shown for `durations[i % durations.len()]` milliseconds,
+/// so a single duration applies to all frames.
+pub struct Animation<'a, S> {
+    source: S,
+    durations: &'a [u32],
+    mode: PlayMode,
+    index: usize,
+    backwards: bool,
+    finished: bool,
+    due: Option<u32>,
+}
+
+impl<'a, S> Animation<'a, S> {
+    /// Creates a new animation starting at the first frame
+    pub fn new(source: S, durations: &'a [u32], mode: PlayMode) -> Self {
+        Animation {
+            source,
+            durations,
+            mode,
+            index: 0,
+            backwards: false,
+            finished: false,
+            due: None,
+        }
+    }
+
+    /// Rewinds to the first frame
+    pub fn reset(&mut self) {
+        self.index = 0;
+        self.backwards = false;
+        self.finished = false;
+        self.due = None;
+    }
+
+    /// Index of the frame shown next
+    pub fn current(&self) -> usize {
+        self.index
+    }
+
+    /// True once a [PlayMode::OneShot] animation has shown its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the frame source
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn duration(&self, index: usize) -> u32 {
+        match self.durations.len() {
+            0 => 0,
+            n => self.durations[index % n],
+        }
+    }
+
+    // moves to the following frame, returns true when a cycle is complete
+    fn advance(&mut self, len: usize) -> bool {
+        if len <= 1 {
+            self.finished = self.mode == PlayMode::OneShot;
+            return true;
+        }
+        match (self.mode, self.backwards) {
+            (PlayMode::PingPong, false) if self.index + 1 == len => {
+                self.backwards = true;
+                self.index -= 1;
+                false
+            }
+            (PlayMode::PingPong, true) => {
+                self.index -= 1;
+                if self.index == 0 {
+                    self.backwards = false;
+                }
+                self.index == 0
+            }
+            (PlayMode::OneShot, _) if self.index + 1 == len => {
+                self.finished = true;
+                true
+            }
+            (_, _) if self.index + 1 == len => {
+                self.index = 0;
+                true
+            }
+            (_, _) => {
+                self.index += 1;
+                false
+            }
+        }
+    }
+
+    /// Shows the current frame and advances
+    ///
+    /// Returns how long the shown frame should stay and whether a cycle was completed,
+    /// or `None` if the animation is finished. A frame which fails to show is not skipped,
+    /// the next call tries it again.
+    pub fn step<SPI, RST, DELAY>(
+        &mut self,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+    ) -> Result<Option<(u32, bool)>, FrameError<SPI::Error>>
+    where
+        S: FrameSource<SPI, RST, DELAY>,
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+    {
+        let len = self.source.len();
+        if self.finished || len == 0 {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.source.show(index, vfd)?;
+        let cycle = self.advance(len);
+        Ok(Some((self.duration(index), cycle)))
+    }
+
+    /// Blocking playback using the delay provider of `vfd`
+    ///
+    /// Returns after the last frame of a [PlayMode::OneShot] animation, or after one full cycle
+    /// otherwise, so looping animations can simply be played again.
+    pub fn play<SPI, RST, DELAY>(
+        &mut self,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+    ) -> Result<(), FrameError<SPI::Error>>
+    where
+        S: FrameSource<SPI, RST, DELAY>,
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+    {
+        while let Some((duration, cycle)) = self.step(vfd)? {
+            vfd.delay_ms(duration);
+            if cycle {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Non blocking playback for cooperative schedulers
+    ///
+    /// `now` is a millisecond timestamp which may wrap around. The next frame is shown once it is due,
+    /// the returned value is the time the following frame is due, or `None` if the animation is finished.
+    pub fn poll<SPI, RST, DELAY>(
+        &mut self,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+        now: u32,
+    ) -> Result<Option<u32>, FrameError<SPI::Error>>
+    where
+        S: FrameSource<SPI, RST, DELAY>,
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+    {
+        if let Some(due) = self.due {
+            // wrapping comparison, due is still in the future
+            if (now.wrapping_sub(due) as i32) < 0 {
+                return Ok(Some(due));
+            }
+        }
+        match self.step(vfd)? {
+            Some((duration, _)) => {
+                let due = now.wrapping_add(duration);
+                self.due = Some(due);
+                Ok(Some(due))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(mode: PlayMode, len: usize, steps: usize) -> [usize; 8] {
+        let mut animation = Animation::new((), &[], mode);
+        let mut out = [usize::MAX; 8];
+        for slot in out.iter_mut().take(steps) {
+            if animation.is_finished() {
+                break;
+            }
+            *slot = animation.current();
+            animation.advance(len);
+        }
+        out
+    }
+
+    #[test]
+    fn decode_errors_are_returned() {
+        use crate::codec::{encode, CodecError};
+        use crate::gp1287bi::NUM_DISPLAY_BITS;
+        use crate::model::{Gp1287Model, NoopDelay, NoopPin};
+
+        // a key frame which doesn't compress and a delta frame changing a single byte
+        let key: [u8; NUM_DISPLAY_BITS as usize] = core::array::from_fn(|i| (i * 7) as u8);
+        let mut delta = key;
+        delta[100] ^= 0xff;
+        let frames = [key, delta];
+        let mut out = [0u8; 4096];
+        let len = encode(frames.iter().map(|f| &f[..]), 2, &mut out).unwrap();
+        let stream = FrameStream::new(&out[..len], NUM_DISPLAY_BITS as usize).unwrap();
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+
+        // the delta frame needs a decoder depth of 2
+        let mut animation = Animation::new(CompressedFrames::<1>(stream), &[10], PlayMode::Loop);
+        assert_eq!(animation.step(&mut vfd), Ok(Some((10, false))));
+        assert_eq!(
+            animation.step(&mut vfd),
+            Err(FrameError::Codec(CodecError::ChainTooLong))
+        );
+        assert_eq!(animation.current(), 1);
+
+        // a stream made for another display size
+        let len = encode([&[0u8; 64][..]], 1, &mut out).unwrap();
+        let stream = FrameStream::new(&out[..len], 64).unwrap();
+        let mut animation = Animation::new(CompressedFrames::<1>(stream), &[], PlayMode::Loop);
+        assert_eq!(
+            animation.poll(&mut vfd, 0),
+            Err(FrameError::Codec(CodecError::LengthMismatch))
+        );
+    }
+
+    #[test]
+    fn frame_order() {
+        const X: usize = usize::MAX;
+        assert_eq!(sequence(PlayMode::OneShot, 3, 8), [0, 1, 2, X, X, X, X, X]);
+        assert_eq!(sequence(PlayMode::Loop, 3, 8), [0, 1, 2, 0, 1, 2, 0, 1]);
+        assert_eq!(sequence(PlayMode::PingPong, 3, 8), [0, 1, 2, 1, 0, 1, 2, 1]);
+    }
+}
@@ -0,0 +1,462 @@
+//! Compressed frame storage
+//!
+//! Frames are run-length encoded, either on their own (key frames) or as the XOR against
+//! the previous frame (delta frames), which is mostly zeros for animations.
+//!
+//! A stream is a sequence of frames:
+//!
+//! ```text
+//! frame   := kind:u8 len:u16le payload[len]
+//! kind    := 0x00 (key) | 0x01 (delta)
+//! payload := op*
+//! op      := 0x00..=0x7f, then op + 1 literal bytes
+//!          | 0x80..=0xff, then one byte repeated op - 0x80 + 2 times
+//! ```
+//!
+//! Decoding never needs a frame in RAM: a delta frame is the XOR of all payloads back to the
+//! last key frame, so [FrameDecoder] runs one small cursor per payload in the chain and
+//! combines them byte by byte. The encoder inserts key frames so the chain stays short.
+
+const KEY_FRAME: u8 = 0x00;
+const DELTA_FRAME: u8 = 0x01;
+const HEADER_LEN: usize = 3;
+const MAX_LITERAL: usize = 128;
+const MAX_REPEAT: usize = 129;
+
+/// Errors while encoding or decoding a frame stream
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CodecError {
+    /// The output buffer is too small for the encoded stream
+    BufferTooSmall,
+    /// A frame does not have the expected length
+    LengthMismatch,
+    /// The stream ends in the middle of a frame
+    Truncated,
+    /// A frame has an unknown kind or does not decode to exactly one frame
+    Malformed,
+    /// The stream starts with a delta frame
+    MissingKeyFrame,
+    /// More delta frames since the last key frame than the decoder can follow
+    ChainTooLong,
+    /// The requested frame is not part of the stream
+    OutOfRange,
+}
+
+impl core::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self {
+            CodecError::BufferTooSmall => "Output buffer too small",
+            CodecError::LengthMismatch => "Frame length mismatch",
+            CodecError::Truncated => "Truncated frame stream",
+            CodecError::Malformed => "Malformed frame",
+            CodecError::MissingKeyFrame => "Stream does not start with a key frame",
+            CodecError::ChainTooLong => "Too many delta frames since the last key frame",
+            CodecError::OutOfRange => "Frame index out of range",
+        };
+        f.write_str(msg)
+    }
+}
+
+/// Errors while sending a compressed or saved frame to a display
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrameError<E> {
+    /// The frame could not be decoded or does not fit the display
+    Codec(CodecError),
+    /// Writing to the display failed
+    Spi(E),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for FrameError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FrameError::Codec(e) => write!(f, "Decoding failed: {}", e),
+            FrameError::Spi(e) => write!(f, "Writing failed: {}", e),
+        }
+    }
+}
+
+impl<E> From<CodecError> for FrameError<E> {
+    fn from(e: CodecError) -> Self {
+        FrameError::Codec(e)
+    }
+}
+
+/// Encodes `frames` into `out` and returns the number of bytes written
+///
+/// A key frame is emitted at least every `key_interval` frames (and whenever it is smaller
+/// than the delta), so a [FrameDecoder] with a depth of `key_interval` can decode every frame.
+pub fn encode<'f, I>(frames: I, key_interval: usize, out: &mut [u8]) -> Result<usize, CodecError>
+where
+    I: IntoIterator<Item = &'f [u8]>,
+{
+    let mut pos = 0;
+    let mut previous: Option<&[u8]> = None;
+    let mut chain = 0;
+    for frame in frames {
+        if previous.is_some_and(|prev| prev.len() != frame.len()) {
+            return Err(CodecError::LengthMismatch);
+        }
+        let delta = match previous {
+            Some(prev) if chain < key_interval => {
+                let xor = |i: usize| frame[i] ^ prev[i];
+                let key_len = rle(&|i| frame[i], frame.len(), None)?;
+                let delta_len = rle(&xor, frame.len(), None)?;
+                delta_len < key_len
+            }
+            _ => false,
+        };
+
+        let (kind, len) = match (delta, previous) {
+            (true, Some(prev)) => {
+                chain += 1;
+                let xor = |i: usize| frame[i] ^ prev[i];
+                (
+                    DELTA_FRAME,
+                    rle(&xor, frame.len(), Some(payload_out(out, pos)?))?,
+                )
+            }
+            _ => {
+                chain = 1;
+                (
+                    KEY_FRAME,
+                    rle(&|i| frame[i], frame.len(), Some(payload_out(out, pos)?))?,
+                )
+            }
+        };
+        let header = out
+            .get_mut(pos..pos + HEADER_LEN)
+            .ok_or(CodecError::BufferTooSmall)?;
+        let len16 = u16::try_from(len).map_err(|_| CodecError::BufferTooSmall)?;
+        header[0] = kind;
+        header[1..].copy_from_slice(&len16.to_le_bytes());
+        pos += HEADER_LEN + len;
+        previous = Some(frame);
+    }
+    Ok(pos)
+}
+
+fn payload_out(out: &mut [u8], pos: usize) -> Result<&mut [u8], CodecError> {
+    out.get_mut(pos + HEADER_LEN..)
+        .ok_or(CodecError::BufferTooSmall)
+}
+
+// PackBits style run-length encoding, with `out` set to None only the length is computed
+fn rle(
+    src: &dyn Fn(usize) -> u8,
+    len: usize,
+    mut out: Option<&mut [u8]>,
+) -> Result<usize, CodecError> {
+    let mut written = 0;
+    let mut emit = |byte: u8| -> Result<(), CodecError> {
+        if let Some(out) = out.as_deref_mut() {
+            *out.get_mut(written).ok_or(CodecError::BufferTooSmall)? = byte;
+        }
+        written += 1;
+        Ok(())
+    };
+    let run_at = |i: usize| {
+        let value = src(i);
+        (i..len.min(i + MAX_REPEAT))
+            .take_while(|j| src(*j) == value)
+            .count()
+    };
+
+    let mut i = 0;
+    while i < len {
+        let run = run_at(i);
+        if run >= 3 {
+            emit(0x80 | (run - 2) as u8)?;
+            emit(src(i))?;
+            i += run;
+            continue;
+        }
+        let start = i;
+        while i < len && i - start < MAX_LITERAL && (i == start || run_at(i) < 3) {
+            i += 1;
+        }
+        emit((i - start - 1) as u8)?;
+        for j in start..i {
+            emit(src(j))?;
+        }
+    }
+    Ok(written)
+}
+
+/// A validated stream of encoded frames
+#[derive(Clone, Copy)]
+pub struct FrameStream<'a> {
+    data: &'a [u8],
+    frame_len: usize,
+    frames: usize,
+}
+
+impl<'a> FrameStream<'a> {
+    /// Checks the whole stream once, so decoding it later can not fail
+    ///
+    /// `frame_len` is the size of a decoded frame, e.g. [buffer_len](crate::buffer_len) of the display.
+    pub fn new(data: &'a [u8], frame_len: usize) -> Result<Self, CodecError> {
+        let mut stream = FrameStream {
+            data,
+            frame_len,
+            frames: 0,
+        };
+        let mut pos = 0;
+        while pos < data.len() {
+            let (kind, payload) = stream.frame_at(pos)?;
+            if kind == DELTA_FRAME && stream.frames == 0 {
+                return Err(CodecError::MissingKeyFrame);
+            }
+            let mut cursor = Cursor::new(payload);
+            for _ in 0..frame_len {
+                cursor.next().ok_or(CodecError::Malformed)?;
+            }
+            if !cursor.is_done() {
+                return Err(CodecError::Malformed);
+            }
+            pos += HEADER_LEN + payload.len();
+            stream.frames += 1;
+        }
+        Ok(stream)
+    }
+
+    /// Number of frames in the stream
+    pub fn len(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns true if the stream contains no frames
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Size of one decoded frame in bytes
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Creates a decoder for frame `index`
+    ///
+    /// `DEPTH` is the maximal number of frames back to the last key frame, each costs a few bytes of RAM.
+    pub fn decoder<const DEPTH: usize>(
+        &self,
+        index: usize,
+    ) -> Result<FrameDecoder<'a, DEPTH>, CodecError> {
+        if index >= self.frames {
+            return Err(CodecError::OutOfRange);
+        }
+        let mut decoder = FrameDecoder {
+            cursors: [Cursor::default(); DEPTH],
+            chain: 0,
+            remaining: self.frame_len,
+        };
+        let mut pos = 0;
+        for _ in 0..=index {
+            let (kind, payload) = self.frame_at(pos)?;
+            if kind == KEY_FRAME {
+                decoder.chain = 0;
+            }
+            // only the chain of frame `index` has to fit, earlier ones are skipped
+            if let Some(cursor) = decoder.cursors.get_mut(decoder.chain) {
+                *cursor = Cursor::new(payload);
+            }
+            decoder.chain += 1;
+            pos += HEADER_LEN + payload.len();
+        }
+        if decoder.chain > DEPTH {
+            return Err(CodecError::ChainTooLong);
+        }
+        Ok(decoder)
+    }
+
+    fn frame_at(&self, pos: usize) -> Result<(u8, &'a [u8]), CodecError> {
+        let header = self
+            .data
+            .get(pos..pos + HEADER_LEN)
+            .ok_or(CodecError::Truncated)?;
+        if header[0] != KEY_FRAME && header[0] != DELTA_FRAME {
+            return Err(CodecError::Malformed);
+        }
+        let len = u16::from_le_bytes([header[1], header[2]]) as usize;
+        let payload = self
+            .data
+            .get(pos + HEADER_LEN..pos + HEADER_LEN + len)
+            .ok_or(CodecError::Truncated)?;
+        Ok((header[0], payload))
+    }
+}
+
+/// Decodes one frame of a [FrameStream] piece by piece
+///
+/// Also an iterator over the bytes of the decoded frame.
+pub struct FrameDecoder<'a, const DEPTH: usize> {
+    cursors: [Cursor<'a>; DEPTH],
+    chain: usize,
+    remaining: usize,
+}
+
+impl<'a, const DEPTH: usize> FrameDecoder<'a, DEPTH> {
+    /// Decodes the next bytes of the frame into `chunk` and returns how many were written
+    ///
+    /// Returns 0 once the whole frame has been decoded.
+    pub fn fill(&mut self, chunk: &mut [u8]) -> usize {
+        let mut n = 0;
+        for (dst, byte) in chunk.iter_mut().zip(&mut *self) {
+            *dst = byte;
+            n += 1;
+        }
+        n
+    }
+
+    /// Number of bytes left in the frame
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'a, const DEPTH: usize> Iterator for FrameDecoder<'a, DEPTH> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(
+            self.cursors[..self.chain]
+                .iter_mut()
+                .fold(0, |acc, cursor| acc ^ cursor.next().unwrap_or(0)),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, const DEPTH: usize> ExactSizeIterator for FrameDecoder<'a, DEPTH> {}
+
+// state of the run-length decoder for a single payload
+#[derive(Clone, Copy, Default)]
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    literal: usize,
+    repeat: usize,
+    value: u8,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Cursor {
+            data,
+            ..Default::default()
+        }
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        if self.literal == 0 && self.repeat == 0 {
+            let op = *self.data.get(self.pos)? as usize;
+            self.pos += 1;
+            if op < 0x80 {
+                self.literal = op + 1;
+            } else {
+                self.value = *self.data.get(self.pos)?;
+                self.pos += 1;
+                self.repeat = op - 0x80 + 2;
+            }
+        }
+        if self.repeat > 0 {
+            self.repeat -= 1;
+            return Some(self.value);
+        }
+        let byte = *self.data.get(self.pos)?;
+        self.pos += 1;
+        self.literal -= 1;
+        Some(byte)
+    }
+
+    fn is_done(&self) -> bool {
+        self.literal == 0 && self.repeat == 0 && self.pos == self.data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEN: usize = 64;
+
+    fn frames() -> [[u8; LEN]; 5] {
+        let mut frames = [[0u8; LEN]; 5];
+        for (n, frame) in frames.iter_mut().enumerate() {
+            for (i, byte) in frame.iter_mut().enumerate() {
+                *byte = if i == n * 3 { 0xff } else { (i * 7) as u8 };
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn roundtrip() {
+        let frames = frames();
+        let mut out = [0u8; 512];
+        let len = encode(frames.iter().map(|f| &f[..]), 3, &mut out).unwrap();
+        let stream = FrameStream::new(&out[..len], LEN).unwrap();
+        assert_eq!(stream.len(), frames.len());
+
+        for (index, frame) in frames.iter().enumerate() {
+            let mut decoder = stream.decoder::<3>(index).unwrap();
+            let mut decoded = [0u8; LEN];
+            // decode in odd sized chunks
+            let mut pos = 0;
+            while pos < LEN {
+                pos += decoder.fill(&mut decoded[pos..(pos + 5).min(LEN)]);
+            }
+            assert_eq!(decoder.fill(&mut [0u8; 4]), 0);
+            assert_eq!(&decoded, frame);
+        }
+    }
+
+    #[test]
+    fn chain_depth() {
+        let frames = frames();
+        let mut out = [0u8; 512];
+        let len = encode(frames.iter().map(|f| &f[..]), 4, &mut out).unwrap();
+        let stream = FrameStream::new(&out[..len], LEN).unwrap();
+        assert_eq!(stream.decoder::<1>(3).err(), Some(CodecError::ChainTooLong));
+        assert_eq!(stream.decoder::<4>(5).err(), Some(CodecError::OutOfRange));
+        // a key frame after a long chain
+        let decoded: [u8; LEN] = core::array::from_fn({
+            let mut decoder = stream.decoder::<1>(4).unwrap();
+            move |_| decoder.next().unwrap()
+        });
+        assert_eq!(decoded, frames[4]);
+    }
+
+    #[test]
+    fn rejects_mixed_lengths() {
+        let frames = frames();
+        let mut out = [0u8; 512];
+        assert_eq!(
+            encode([&frames[0][..], &frames[1][..8]], 1, &mut out),
+            Err(CodecError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert_eq!(
+            FrameStream::new(&[DELTA_FRAME, 2, 0, 0x80 | 2, 0], 4).err(),
+            Some(CodecError::MissingKeyFrame)
+        );
+        // decodes to 3 instead of 4 bytes
+        assert_eq!(
+            FrameStream::new(&[KEY_FRAME, 2, 0, 0x81, 0], 4).err(),
+            Some(CodecError::Malformed)
+        );
+        assert_eq!(
+            FrameStream::new(&[KEY_FRAME, 9, 0, 0x82, 0], 4).err(),
+            Some(CodecError::Truncated)
+        );
+    }
+}
@@ -1,8 +1,10 @@
 //! B/W Color for EPDs
 
-use embedded_graphics_core::pixelcolor::raw::{RawData, RawU8};
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::pixelcolor::raw::{RawData, RawU1};
 #[cfg(feature = "graphics")]
 use embedded_graphics_core::pixelcolor::BinaryColor;
+#[cfg(feature = "graphics")]
 use embedded_graphics_core::prelude::PixelColor;
 
 #[cfg(feature = "graphics")]
@@ -20,7 +22,7 @@ impl core::fmt::Display for OutOfColorRangeParseError {
 }
 
 impl OutOfColorRangeParseError {
-    fn _new(size: u8) -> OutOfColorRangeParseError {
+    fn new(size: u8) -> OutOfColorRangeParseError {
         OutOfColorRangeParseError(size)
     }
 }
@@ -34,8 +36,9 @@ pub enum Color {
     Green,
 }
 
+#[cfg(feature = "graphics")]
 impl PixelColor for Color {
-    type Raw = RawU8;
+    type Raw = RawU1;
 }
 
 impl Color {
@@ -55,14 +58,6 @@ impl Color {
         }
     }
 
-    /// Parses from u8 to Color
-    fn from_u8(val: u8) -> Self {
-        match val {
-            0 => Color::Dark,
-            _ => Color::Green,
-        }
-    }
-
     /// Returns the inverse of the given color.
     ///
     /// Black returns White and White returns Black
@@ -74,15 +69,53 @@ impl Color {
     }
 }
 
-impl From<u8> for Color {
-    fn from(value: u8) -> Self {
-        Color::from_u8(value)
+impl TryFrom<u8> for Color {
+    type Error = OutOfColorRangeParseError;
+
+    /// Parses a bit value, everything besides 0 and 1 is out of range
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Color::Dark),
+            1 => Ok(Color::Green),
+            _ => Err(OutOfColorRangeParseError::new(value)),
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<RawU1> for Color {
+    fn from(val: RawU1) -> Self {
+        match val.into_inner() {
+            0 => Color::Dark,
+            _ => Color::Green,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<Color> for RawU1 {
+    fn from(color: Color) -> Self {
+        RawU1::new(color.get_bit_value())
     }
 }
 
-impl From<RawU8> for Color {
-    fn from(val: RawU8) -> Self {
-        Self::from_u8(val.into_inner())
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for Color {
+    fn from(color: BinaryColor) -> Self {
+        match color {
+            BinaryColor::On => Color::Green,
+            BinaryColor::Off => Color::Dark,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<Color> for BinaryColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Green => BinaryColor::On,
+            Color::Dark => BinaryColor::Off,
+        }
     }
 }
 
@@ -92,29 +125,56 @@ mod tests {
 
     #[test]
     fn from_u8() {
-        assert_eq!(Color::Dark, Color::from(0u8));
-        assert_eq!(Color::Green, Color::from(1u8));
+        assert_eq!(Ok(Color::Dark), Color::try_from(0u8));
+        assert_eq!(Ok(Color::Green), Color::try_from(1u8));
     }
 
-    // test all values aside from 0 and 1 which all should panic
+    // test all values aside from 0 and 1 which all should be out of range
     #[test]
-    fn from_u8_panic() {
-        for val in 2..=u8::max_value() {
-            extern crate std;
-            let result = std::panic::catch_unwind(|| Color::from(val));
-            assert!(result.is_err());
+    fn from_u8_out_of_range() {
+        for val in 2..=u8::MAX {
+            assert_eq!(Color::try_from(val), Err(OutOfColorRangeParseError(val)));
         }
     }
 
     #[test]
     fn u8_conversion_black() {
-        assert_eq!(Color::from(Color::Dark.get_bit_value()), Color::Dark);
-        assert_eq!(Color::from(0u8).get_bit_value(), 0u8);
+        assert_eq!(
+            Color::try_from(Color::Dark.get_bit_value()),
+            Ok(Color::Dark)
+        );
+        assert_eq!(Color::try_from(0u8).unwrap().get_bit_value(), 0u8);
     }
 
     #[test]
     fn u8_conversion_white() {
-        assert_eq!(Color::from(Color::Green.get_bit_value()), Color::Green);
-        assert_eq!(Color::from(1u8).get_bit_value(), 1u8);
+        assert_eq!(
+            Color::try_from(Color::Green.get_bit_value()),
+            Ok(Color::Green)
+        );
+        assert_eq!(Color::try_from(1u8).unwrap().get_bit_value(), 1u8);
+    }
+
+    #[test]
+    fn binary_color_conversion() {
+        assert_eq!(Color::from(BinaryColor::On), Color::Green);
+        assert_eq!(Color::from(BinaryColor::Off), Color::Dark);
+        assert_eq!(BinaryColor::from(Color::Green), BinaryColor::On);
+        assert_eq!(BinaryColor::from(Color::Dark), BinaryColor::Off);
+    }
+
+    #[test]
+    fn image_raw_is_one_bit_per_pixel() {
+        use crate::assert_display_eq;
+        use crate::graphics::VarDisplay;
+        use embedded_graphics::image::{Image, ImageRaw};
+        use embedded_graphics::prelude::*;
+
+        let data = [0b1010_0000, 0b0110_0000];
+        let raw = ImageRaw::<Color>::new(&data, 4);
+        let mut buffer = [0u8; 2];
+        let mut display = VarDisplay::new(4, 2, &mut buffer);
+        Image::new(&raw, Point::zero()).draw(&mut display).unwrap();
+        assert_display_eq!(display, "#.#.\n.##.");
     }
 }
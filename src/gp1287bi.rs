@@ -2,7 +2,8 @@
 
 use embedded_hal::{delay::DelayNs, digital::*, spi::SpiDevice};
 
-use crate::interface::DisplayInterface;
+use crate::interface::{BitOrder, DisplayInterface, TransferMode};
+use crate::power::PowerLimit;
 use crate::traits::{EEIDisplay, EEIInit};
 
 /// Width of gp1287bi in pixels
@@ -11,22 +12,70 @@ pub const WIDTH: u32 = 56;
 pub const HEIGHT: u32 = 256;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::Dark;
-const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
+pub(crate) const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
+/// Bytes of one buffer row, which is one column of the GRAM
+pub(crate) const ROW_BYTES: usize = buffer_len(WIDTH as usize, 1);
+/// Rows sent per transaction when streaming a frame
+const CHUNK_ROWS: usize = 8;
+
+use crate::buffer_len;
+use crate::codec::{CodecError, FrameDecoder, FrameError};
 
 use crate::color::Color;
+#[cfg(feature = "graphics")]
+use crate::graphics::{
+    BandError, Display, DisplayRotation, StripDirection, VarDisplay, VarDisplayError,
+};
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
 
 pub(crate) mod command;
 
+mod area;
+mod display_mode;
 #[cfg(feature = "graphics")]
 mod graphics;
+mod lit;
+mod resilient;
+#[cfg(feature = "graphics")]
+mod tiled;
+mod timing;
 
+use self::area::Area;
 use self::command::Command;
+pub use self::display_mode::DisplayMode;
 #[cfg(feature = "graphics")]
 pub use self::graphics::Display256x50;
+#[cfg(feature = "graphics")]
+pub(crate) use self::lit::glass_lit;
+use self::lit::{frame_lit, LitPixels};
+pub use self::resilient::{Resilient, RetryPolicy};
+#[cfg(feature = "graphics")]
+pub use self::tiled::{Tile, TiledDisplay};
+pub use self::timing::{FrameSync, FrameTiming, Oscillator, PresentMode, ScanSpeed};
+use self::timing::{FRAME_SYNC_OFF, FRAME_SYNC_ON};
 
 /// vfd1in02 driver
 pub struct VFD256x50<SPI, RST, DELAY> {
     interface: DisplayInterface<SPI, RST, DELAY>,
+    /// sent after every reset and wake up, None until set
+    oscillator: Option<Oscillator>,
+    scan_speed: ScanSpeed,
+    frame_sync: FrameSync,
+    /// frame sync setting of the controller is on
+    synced: bool,
+    display_mode: DisplayMode,
+    /// scanned part of the glass, the GRAM is remapped to start with it
+    area: Area,
+    /// brightness asked for, before the power limit
+    brightness: u32,
+    /// brightness last sent to the controller
+    applied_brightness: u32,
+    power_limit: Option<PowerLimit>,
+    /// set bits of the GRAM, for the power limit
+    lit: LitPixels,
+    /// GRAM writes so far, wrapping
+    gram_writes: u32,
 }
 
 impl<SPI, RST, DELAY> EEIInit<SPI, RST, DELAY> for VFD256x50<SPI, RST, DELAY>
@@ -46,18 +95,17 @@ where
         self.cmd_with_args(Command::VFDModeSetting, &[0x02, 0x00])?;
 
         // set display area
-        self.cmd_with_args(
-            Command::DisplayAreaSetting,
-            &[0xFF, 0x31, 0x00, 0x20, 0x00, 0x00, 0x80],
-        )?;
+        self.cmd_with_args(Command::DisplayAreaSetting, &self.area.args())?;
 
         // set internal speed
-        self.cmd_with_args(Command::InternalSpeedSetting, &[0x20, 0x3F, 0x00, 0x01])?;
+        self.send_timing()?;
 
         // set brightness
         self.set_brightness(0x30)?;
 
         // clear gram
+        self.lit = LitPixels::DARK;
+        self.gram_writes = self.gram_writes.wrapping_add(1);
         self.command(Command::ClearGRAM)?;
         self.interface.delay.delay_ms(10);
 
@@ -69,10 +117,11 @@ where
         self.cmd_with_args(Command::UnknownInit, &[0x00])?;
 
         // set display mode
-        self.cmd_with_args(Command::DisplayModeSetting, &[0x00])?;
+        self.cmd_with_args(Command::DisplayModeSetting, &[self.display_mode.arg()])?;
 
         // set frame sync
-        self.cmd_with_args(Command::FrameSyncSetting, &[0x00])
+        self.synced = false;
+        self.cmd_with_args(Command::FrameSyncSetting, &[FRAME_SYNC_OFF])
     }
 }
 
@@ -84,20 +133,13 @@ where
 {
     type DisplayColor = Color;
     fn new(spi: SPI, rst: RST, delay: DELAY) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(spi, rst, delay);
-
-        let mut vfd = VFD256x50 { interface };
-
-        vfd.init()?;
-
-        Ok(vfd)
+        Self::with_bit_order(spi, rst, delay, BitOrder::default())
     }
 
+    /// Lowered as far as needed to stay within the power limit
     fn set_brightness(&mut self, val: u32) -> Result<(), SPI::Error> {
-        self.cmd_with_args(
-            Command::BrightnessSetting,
-            &[((val >> 8) as u8) & 0b11, val as u8],
-        )
+        self.brightness = val;
+        self.send_brightness(self.limited_brightness())
     }
 
     fn sleep(&mut self) -> Result<(), SPI::Error> {
@@ -105,7 +147,8 @@ where
     }
 
     fn wake_up(&mut self) -> Result<(), SPI::Error> {
-        self.command(Command::WakeUp)
+        self.command(Command::WakeUp)?;
+        self.send_timing()
     }
 
     fn width(&self) -> u32 {
@@ -117,10 +160,13 @@ where
     }
 
     fn update_frame(&mut self, buffer: &[u8]) -> Result<(), SPI::Error> {
-        self.cmd_with_data(Command::WriteGRAM, &[0x00, 0x04, 0x37], buffer)
+        let lit = frame_lit(buffer, self.display_mode, self.area);
+        self.power_limited(Some(lit), |vfd| vfd.write_gram(0, buffer))
     }
 
-    #[allow(unused)]
+    /// The buffer rows are `width.div_ceil(8)` bytes. If `x` is not a multiple of 8, the pixels
+    /// are shifted into place and the other pixels sharing a byte with the left or right edge
+    /// are cleared, the GRAM is written in whole bytes.
     fn update_partial_frame(
         &mut self,
         buffer: &[u8],
@@ -129,14 +175,19 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), SPI::Error> {
-        unimplemented!()
+        let Some(block) = PartialBlock::new(buffer.len(), x, y, width, height) else {
+            return Ok(());
+        };
+        self.power_limited(None, |vfd| vfd.write_partial(buffer, width, block))
     }
 
     fn clear_frame(&mut self) -> Result<(), SPI::Error> {
         // Clear the black
+        self.lit = LitPixels::DARK;
+        self.gram_writes = self.gram_writes.wrapping_add(1);
         self.command(Command::ClearGRAM)?;
         self.interface.delay.delay_ms(10);
-        Ok(())
+        self.follow_power_limit()
     }
 }
 
@@ -146,6 +197,546 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
+    /// Like [EEIDisplay::new], for a bus which is not configured MSB first
+    pub fn with_bit_order(
+        spi: SPI,
+        rst: RST,
+        delay: DELAY,
+        bit_order: BitOrder,
+    ) -> Result<Self, SPI::Error> {
+        let mut interface = DisplayInterface::new(spi, rst, delay);
+        interface.bit_order = bit_order;
+
+        let mut vfd = VFD256x50 {
+            interface,
+            oscillator: None,
+            scan_speed: ScanSpeed::default(),
+            frame_sync: FrameSync::default(),
+            synced: false,
+            display_mode: DisplayMode::default(),
+            area: Area::FULL,
+            brightness: 0,
+            applied_brightness: 0,
+            power_limit: None,
+            lit: LitPixels::DARK,
+            gram_writes: 0,
+        };
+
+        vfd.init()?;
+
+        Ok(vfd)
+    }
+
+    /// Resets and initializes the controller again, keeping all settings
+    pub(crate) fn reinit(&mut self) -> Result<(), SPI::Error> {
+        // init starts with the default brightness
+        let brightness = self.brightness;
+        self.init()?;
+        self.set_brightness(brightness)
+    }
+
+    /// Returns the bit order the bus is expected to use
+    pub fn bit_order(&self) -> BitOrder {
+        self.interface.bit_order
+    }
+
+    /// Streams a compressed frame into the GRAM
+    ///
+    /// The frame is decoded in small chunks, each chunk is written to its own position in the GRAM,
+    /// so the decoded frame never has to fit into RAM. Frames of a stream with another frame
+    /// length than the display are rejected with [CodecError::LengthMismatch] before anything
+    /// is sent.
+    pub fn update_frame_compressed<const DEPTH: usize>(
+        &mut self,
+        frame: FrameDecoder<'_, DEPTH>,
+    ) -> Result<(), FrameError<SPI::Error>> {
+        if frame.remaining() != NUM_DISPLAY_BITS as usize {
+            return Err(CodecError::LengthMismatch.into());
+        }
+        self.update_frame_from_iter(frame).map_err(FrameError::Spi)
+    }
+
+    /// Streams packed frame bytes from an iterator into the GRAM
+    ///
+    /// The bytes are laid out like the buffer for [EEIDisplay::update_frame], but generated
+    /// on demand, e.g. for test patterns. They are sent in small chunks, so no frame buffer
+    /// is needed. Sending stops after a whole frame or once the iterator ends, in which case
+    /// a trailing partial buffer row is dropped.
+    pub fn update_frame_from_iter<I>(&mut self, bytes: I) -> Result<(), SPI::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        self.power_limited(None, |vfd| vfd.stream_frame(bytes))
+    }
+
+    fn stream_frame<I>(&mut self, bytes: I) -> Result<(), SPI::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut bytes = bytes.into_iter().take(NUM_DISPLAY_BITS as usize);
+        let mut chunk = [0u8; ROW_BYTES * CHUNK_ROWS];
+        let mut row = 0;
+        loop {
+            let mut len = 0;
+            for (slot, byte) in chunk.iter_mut().zip(&mut bytes) {
+                *slot = byte;
+                len += 1;
+            }
+            let len = len - len % ROW_BYTES;
+            if len == 0 {
+                return Ok(());
+            }
+            self.write_gram(row, &chunk[..len])?;
+            row += len / ROW_BYTES;
+        }
+    }
+
+    /// Sets every byte of the GRAM to `byte`, without a buffer
+    ///
+    /// `0x00` clears the display like [EEIDisplay::clear_frame] without waiting for it,
+    /// other values draw simple stripe patterns.
+    pub fn fill_gram(&mut self, byte: u8) -> Result<(), SPI::Error> {
+        let mut lit = LitPixels::DARK;
+        lit.fill(byte);
+        let lit = lit.count(self.display_mode, self.area);
+        self.power_limited(Some(lit), |vfd| vfd.fill(byte))
+    }
+
+    fn fill(&mut self, byte: u8) -> Result<(), SPI::Error> {
+        self.lit.fill(byte);
+        self.gram_writes = self.gram_writes.wrapping_add(1);
+        const LEN: usize = ROW_BYTES * CHUNK_ROWS;
+        let fits = self.interface.max_data(3).is_none_or(|max| max >= LEN);
+        for row in (0..HEIGHT as usize).step_by(CHUNK_ROWS) {
+            if fits {
+                let args = gram_args(row, 0, ROW_BYTES);
+                self.interface
+                    .data_x_times::<LEN, _>(Command::WriteGRAM, &args, byte)?;
+            } else {
+                self.write_gram(row, &[byte; LEN])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders and transmits the frame strip by strip, for targets without RAM for a whole frame
+    ///
+    /// `draw` is called once per strip with a display of the full size and `rotation`,
+    /// which only keeps the pixels of the current strip. It has to draw the whole frame
+    /// each time, starting from a dark background. The strips are as large as `scratch` allows,
+    /// the GRAM ends up the same as with [Display256x50] and [EEIDisplay::update_frame].
+    ///
+    /// Fails with [BandError::Buffer] before anything is sent if `scratch` can't hold a single
+    /// buffer row (horizontal) or eight pixel columns (vertical). An error of `draw` stops
+    /// the frame after the strips sent so far.
+    #[cfg(feature = "graphics")]
+    pub fn update_frame_banded<F, D>(
+        &mut self,
+        scratch: &mut [u8],
+        direction: StripDirection,
+        rotation: DisplayRotation,
+        mut draw: F,
+    ) -> Result<(), BandError<SPI::Error, D>>
+    where
+        F: FnMut(&mut VarDisplay<'_>) -> Result<(), D>,
+    {
+        // strip length along the direction, in buffer rows or bytes of a row
+        let (total, unit) = match direction {
+            StripDirection::Horizontal => (HEIGHT as usize, ROW_BYTES),
+            StripDirection::Vertical => (ROW_BYTES, HEIGHT as usize),
+        };
+        let step = (scratch.len() / unit).min(total);
+        if step == 0 {
+            return Err(BandError::Buffer(VarDisplayError::BufferTooSmall {
+                required: unit,
+                actual: scratch.len(),
+            }));
+        }
+        for start in (0..total).step_by(step) {
+            let len = step.min(total - start);
+            let clip = match direction {
+                StripDirection::Horizontal => {
+                    Rectangle::new(Point::new(0, start as i32), Size::new(WIDTH, len as u32))
+                }
+                StripDirection::Vertical => Rectangle::new(
+                    Point::new(start as i32 * 8, 0),
+                    Size::new(len as u32 * 8, HEIGHT),
+                ),
+            };
+            let mut strip =
+                VarDisplay::strip(WIDTH, HEIGHT, clip, scratch).map_err(BandError::Buffer)?;
+            strip.set_rotation(rotation);
+            strip.clear_buffer(DEFAULT_BACKGROUND_COLOR);
+            draw(&mut strip).map_err(BandError::Draw)?;
+            let data = &scratch[..len * unit];
+            match direction {
+                StripDirection::Horizontal => self.write_gram(start, data),
+                StripDirection::Vertical => self.write_gram_bytes(0, start, len, data),
+            }
+            .map_err(BandError::Spi)?;
+        }
+        self.follow_power_limit().map_err(BandError::Spi)
+    }
+
+    /// Selects the clock source of the controller
+    ///
+    /// Kept across [EEIDisplay::wake_up] and reinitialization.
+    pub fn set_oscillator(&mut self, oscillator: Oscillator) -> Result<(), SPI::Error> {
+        self.oscillator = Some(oscillator);
+        self.cmd_with_args(Command::OscillationSetting, &[oscillator.arg()])
+    }
+
+    /// Returns the selected clock source
+    pub fn oscillator(&self) -> Oscillator {
+        self.oscillator.unwrap_or_default()
+    }
+
+    /// Sets the scan speed and with it the frame rate, see [ScanSpeed]
+    ///
+    /// Kept across [EEIDisplay::wake_up] and reinitialization.
+    pub fn set_scan_speed(&mut self, speed: ScanSpeed) -> Result<(), SPI::Error> {
+        self.scan_speed = speed;
+        self.cmd_with_args(Command::InternalSpeedSetting, &speed.args())
+    }
+
+    /// Returns the current scan speed
+    pub fn scan_speed(&self) -> ScanSpeed {
+        self.scan_speed
+    }
+
+    /// Sends the oscillator and scan speed settings
+    fn send_timing(&mut self) -> Result<(), SPI::Error> {
+        if let Some(oscillator) = self.oscillator {
+            self.cmd_with_args(Command::OscillationSetting, &[oscillator.arg()])?;
+        }
+        self.cmd_with_args(Command::InternalSpeedSetting, &self.scan_speed.args())
+    }
+
+    /// Switches between showing the GRAM and the hardware test modes
+    ///
+    /// Kept across reinitialization.
+    pub fn set_display_mode(&mut self, mode: DisplayMode) -> Result<(), SPI::Error> {
+        let lit = self.lit.count(mode, self.area);
+        self.power_limited(Some(lit), |vfd| {
+            vfd.display_mode = mode;
+            vfd.cmd_with_args(Command::DisplayModeSetting, &[mode.arg()])
+        })
+    }
+
+    /// Returns the current display mode
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    /// Only scans the part of the glass covering `area`, to save power
+    ///
+    /// `area` is given in the coordinates of a [Display] with `rotation` and widened to
+    /// multiples of 8 pixels across the grids. Writes are remapped to the new area, pixels
+    /// outside of it are dropped, so the GRAM has to be written again afterwards.
+    /// An area outside of the display is ignored. Kept across reinitialization.
+    #[cfg(feature = "graphics")]
+    pub fn set_display_area(
+        &mut self,
+        area: Rectangle,
+        rotation: DisplayRotation,
+    ) -> Result<(), SPI::Error> {
+        let physical = crate::graphics::clip_physical(&area, Size::new(WIDTH, HEIGHT), rotation);
+        match physical.as_ref().and_then(Area::covering) {
+            Some(area) => self.apply_area(area),
+            None => Ok(()),
+        }
+    }
+
+    /// Scans the whole glass again, the GRAM has to be written again afterwards
+    pub fn reset_display_area(&mut self) -> Result<(), SPI::Error> {
+        self.apply_area(Area::FULL)
+    }
+
+    /// Returns the scanned part of the glass in unrotated buffer coordinates
+    #[cfg(feature = "graphics")]
+    pub fn display_area(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(self.area.first_byte as i32 * 8, self.area.first_row as i32),
+            Size::new(self.area.bytes as u32 * 8, self.area.rows as u32),
+        )
+    }
+
+    fn apply_area(&mut self, area: Area) -> Result<(), SPI::Error> {
+        let lit = self.lit.count(self.display_mode, area);
+        self.power_limited(Some(lit), |vfd| {
+            vfd.area = area;
+            vfd.cmd_with_args(Command::DisplayAreaSetting, &area.args())
+        })
+    }
+
+    /// Sets how [PresentMode::Synced] avoids tearing
+    pub fn set_frame_sync(&mut self, frame_sync: FrameSync) {
+        self.frame_sync = frame_sync;
+    }
+
+    /// Returns how [PresentMode::Synced] avoids tearing
+    pub fn frame_sync(&self) -> FrameSync {
+        self.frame_sync
+    }
+
+    /// Transmits the buffer of `display`
+    ///
+    /// With [PresentMode::Synced] the controller applies the whole frame at the start of a scan,
+    /// or, with [FrameSync::Timed], the write is spread over several frames.
+    #[cfg(feature = "graphics")]
+    pub fn present<D: Display>(
+        &mut self,
+        display: &D,
+        mode: PresentMode,
+    ) -> Result<(), SPI::Error> {
+        self.present_buffer(display.buffer(), mode)
+    }
+
+    /// Like [VFD256x50::present], for a packed buffer
+    pub fn present_buffer(&mut self, buffer: &[u8], mode: PresentMode) -> Result<(), SPI::Error> {
+        let sync = match mode {
+            PresentMode::Immediate => None,
+            PresentMode::Synced => Some(self.frame_sync),
+        };
+        let synced = sync == Some(FrameSync::Controller);
+        if synced != self.synced {
+            let arg = if synced {
+                FRAME_SYNC_ON
+            } else {
+                FRAME_SYNC_OFF
+            };
+            self.cmd_with_args(Command::FrameSyncSetting, &[arg])?;
+            self.synced = synced;
+        }
+        let lit = frame_lit(buffer, self.display_mode, self.area);
+        self.power_limited(Some(lit), |vfd| match sync {
+            Some(FrameSync::Timed(timing)) => {
+                let band = ROW_BYTES * timing.rows_per_frame.max(1) as usize;
+                for (i, rows) in buffer.chunks(band).enumerate() {
+                    if i > 0 {
+                        vfd.interface.delay.delay_us(timing.frame_us);
+                    }
+                    vfd.write_gram(i * band / ROW_BYTES, rows)?;
+                }
+                Ok(())
+            }
+            _ => vfd.write_gram(0, buffer),
+        })
+    }
+
+    /// Caps the brightness to keep the estimated current within `limit`, None removes the cap
+    ///
+    /// The estimate counts the lit pixels of the GRAM as written by the driver, on the glass
+    /// inside the display area and with the display mode applied. The brightness follows every
+    /// write, it is lowered before whole frames and mode or area changes and adjusted after
+    /// other writes. [EEIDisplay::set_brightness] sets the brightness used while the GRAM
+    /// is within the budget.
+    pub fn set_power_limit(&mut self, limit: Option<PowerLimit>) -> Result<(), SPI::Error> {
+        self.power_limit = limit;
+        self.send_brightness(self.limited_brightness())
+    }
+
+    /// Returns the power limit
+    pub fn power_limit(&self) -> Option<PowerLimit> {
+        self.power_limit
+    }
+
+    /// Returns the brightness asked for with [EEIDisplay::set_brightness]
+    pub fn brightness(&self) -> u32 {
+        self.brightness
+    }
+
+    /// Returns the brightness sent to the controller, lowered by the power limit
+    pub fn applied_brightness(&self) -> u32 {
+        self.applied_brightness
+    }
+
+    /// Runs `change`, adjusting the brightness to the power limit
+    ///
+    /// `lit` is the number of lit pixels after the change, if it is known up front. The
+    /// brightness is then lowered before the change, so the limit holds during it as well.
+    /// Otherwise it follows once the change is done.
+    fn power_limited<F>(&mut self, lit: Option<u32>, change: F) -> Result<(), SPI::Error>
+    where
+        F: FnOnce(&mut Self) -> Result<(), SPI::Error>,
+    {
+        if let (Some(lit), Some(limit)) = (lit, self.power_limit) {
+            let brightness = limit
+                .panel
+                .cap_brightness(lit, self.brightness, limit.budget_ma);
+            if brightness < self.applied_brightness {
+                self.send_brightness(brightness)?;
+            }
+        }
+        change(self)?;
+        self.follow_power_limit()
+    }
+
+    /// Sends the brightness the power limit allows for the current GRAM, if it changed
+    fn follow_power_limit(&mut self) -> Result<(), SPI::Error> {
+        let brightness = self.limited_brightness();
+        if brightness == self.applied_brightness {
+            return Ok(());
+        }
+        self.send_brightness(brightness)
+    }
+
+    fn limited_brightness(&self) -> u32 {
+        match self.power_limit {
+            Some(limit) => {
+                let lit = self.lit.count(self.display_mode, self.area);
+                limit
+                    .panel
+                    .cap_brightness(lit, self.brightness, limit.budget_ma)
+            }
+            None => self.brightness,
+        }
+    }
+
+    fn send_brightness(&mut self, val: u32) -> Result<(), SPI::Error> {
+        self.applied_brightness = val;
+        self.cmd_with_args(
+            Command::BrightnessSetting,
+            &[((val >> 8) as u8) & 0b11, val as u8],
+        )
+    }
+
+    /// Sets how large writes are split up on the bus
+    pub fn set_transfer_mode(&mut self, mode: TransferMode) {
+        self.interface.transfer = mode;
+    }
+
+    /// Returns how large writes are split up on the bus
+    pub fn transfer_mode(&self) -> TransferMode {
+        self.interface.transfer
+    }
+
+    /// Sends everything through `buffer`, for HALs which need `'static` or DMA capable memory
+    ///
+    /// Each transaction is copied into the buffer and written from there, frames stored in
+    /// flash or on the stack never reach the HAL directly. Writes larger than the buffer are
+    /// split up like with [TransferMode::Transactions]. The buffer needs to hold at least
+    /// one buffer row with its GRAM command, 11 bytes, a smaller one is handed back as error.
+    /// Returns the previous buffer.
+    pub fn set_dma_buffer(
+        &mut self,
+        buffer: &'static mut [u8],
+    ) -> Result<Option<&'static mut [u8]>, &'static mut [u8]> {
+        if buffer.len() < ROW_BYTES + 4 {
+            return Err(buffer);
+        }
+        Ok(self.interface.dma_buffer.replace(buffer))
+    }
+
+    /// Stops using the `'static` buffer and returns it
+    pub fn take_dma_buffer(&mut self) -> Option<&'static mut [u8]> {
+        self.interface.dma_buffer.take()
+    }
+
+    /// Counts the GRAM writes, so callers can tell whether a closure wrote to it
+    pub(crate) fn gram_writes(&self) -> u32 {
+        self.gram_writes
+    }
+
+    /// Blocks for `ms` milliseconds using the delay provider of the driver
+    pub(crate) fn delay_ms(&mut self, ms: u32) {
+        self.interface.delay.delay_ms(ms)
+    }
+
+    /// Writes whole buffer rows to the GRAM, starting at `row`
+    fn write_gram(&mut self, row: usize, data: &[u8]) -> Result<(), SPI::Error> {
+        self.write_gram_bytes(row, 0, ROW_BYTES, data)
+    }
+
+    /// Writes `len` bytes from `first` on of each buffer row to the GRAM, starting at `row`
+    ///
+    /// Only the bytes inside of the display area are sent, moved to where the controller
+    /// is assumed to scan them from, which is unverified.
+    fn write_gram_bytes(
+        &mut self,
+        row: usize,
+        first: usize,
+        len: usize,
+        data: &[u8],
+    ) -> Result<(), SPI::Error> {
+        self.lit.write(row, first, len, data);
+        self.gram_writes = self.gram_writes.wrapping_add(1);
+        let area = self.area;
+        if area == Area::FULL {
+            return self.send_gram(row, first, len, data);
+        }
+        let Some((rows, bytes)) = area.clip(row, data.len().div_ceil(len), first, len) else {
+            return Ok(());
+        };
+        if bytes.len() == len {
+            let data = &data[(rows.start - row) * len..data.len().min((rows.end - row) * len)];
+            return self.send_gram(
+                rows.start - area.first_row,
+                first - area.first_byte,
+                len,
+                data,
+            );
+        }
+        for r in rows {
+            let line = &data[(r - row) * len..data.len().min((r - row + 1) * len)];
+            let line = match line.get(bytes.start - first..line.len().min(bytes.end - first)) {
+                Some(line) if !line.is_empty() => line,
+                _ => continue,
+            };
+            self.send_gram(
+                r - area.first_row,
+                bytes.start - area.first_byte,
+                line.len(),
+                line,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes `len` bytes per GRAM column from `first` on, starting at GRAM column `row`
+    fn send_gram(
+        &mut self,
+        row: usize,
+        first: usize,
+        len: usize,
+        data: &[u8],
+    ) -> Result<(), SPI::Error> {
+        // rows per transaction, as many as the transfer mode allows
+        let rows = match self.interface.max_data(3) {
+            Some(max) => (max / len).max(1),
+            None => usize::MAX,
+        };
+        for (i, part) in data.chunks(rows.saturating_mul(len)).enumerate() {
+            let args = gram_args(row + i * rows, first, len);
+            self.cmd_with_data(Command::WriteGRAM, &args, part)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the lines of a partial update, see [EEIDisplay::update_partial_frame]
+    fn write_partial(
+        &mut self,
+        buffer: &[u8],
+        width: u32,
+        block: PartialBlock,
+    ) -> Result<(), SPI::Error> {
+        if block.shift == 0 && block.len == block.stride {
+            // the lines can be sent as they are, in a single write
+            let data = &buffer[..buffer.len().min(block.rows * block.stride)];
+            return self.write_gram_bytes(block.row, block.first, block.len, data);
+        }
+        let mut chunk = [0u8; ROW_BYTES * CHUNK_ROWS];
+        for start in (0..block.rows).step_by(CHUNK_ROWS) {
+            let rows = CHUNK_ROWS.min(block.rows - start);
+            let data = &mut chunk[..rows * block.len];
+            for (i, out) in data.chunks_mut(block.len).enumerate() {
+                shift_line(block.line(buffer, start + i), width, block.shift, out);
+            }
+            self.write_gram_bytes(block.row + start, block.first, block.len, data)?;
+        }
+        Ok(())
+    }
+
     fn command(&mut self, command: Command) -> Result<(), SPI::Error> {
         self.cmd_with_args(command, &[])
     }
@@ -163,3 +754,540 @@ where
         self.interface.cmd_with_data(command, args, data)
     }
 }
+
+/// Where the lines of a partial update end up in the buffer rows
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PartialBlock {
+    /// First buffer row
+    pub(crate) row: usize,
+    /// Lines sent, clipped to the display and the buffer
+    pub(crate) rows: usize,
+    /// First byte of each buffer row
+    pub(crate) first: usize,
+    /// Bytes sent per buffer row
+    pub(crate) len: usize,
+    /// Bytes per line of the partial buffer
+    pub(crate) stride: usize,
+    /// Pixels each line is moved to the right
+    pub(crate) shift: u32,
+}
+
+impl PartialBlock {
+    /// The block of a `width` x `height` update at (`x`, `y`), None if nothing is visible
+    pub(crate) fn new(buffer_len: usize, x: u32, y: u32, width: u32, height: u32) -> Option<Self> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let first = x as usize / 8;
+        let shift = x % 8;
+        let stride = width.div_ceil(8) as usize;
+        let len = ((shift + width).div_ceil(8) as usize).min(ROW_BYTES.saturating_sub(first));
+        let rows = (height as usize)
+            .min(buffer_len.div_ceil(stride.max(1)))
+            .min((HEIGHT as usize).saturating_sub(y as usize));
+        if len == 0 || rows == 0 {
+            return None;
+        }
+        Some(PartialBlock {
+            row: y as usize,
+            rows,
+            first,
+            len,
+            stride,
+            shift,
+        })
+    }
+
+    /// Line `i` of the partial buffer
+    pub(crate) fn line<'a>(&self, buffer: &'a [u8], i: usize) -> &'a [u8] {
+        &buffer[i * self.stride..buffer.len().min((i + 1) * self.stride)]
+    }
+}
+
+/// Writes the first `width` pixels of `line` into `out`, moved `shift` pixels to the right
+///
+/// Bits of `out` not covered by the line are cleared.
+pub(crate) fn shift_line(line: &[u8], width: u32, shift: u32, out: &mut [u8]) {
+    out.fill(0);
+    for (i, byte) in line.iter().enumerate() {
+        let valid = (width as usize).saturating_sub(i * 8).min(8);
+        if valid == 0 {
+            break;
+        }
+        // drop the padding after the last pixel
+        let byte = *byte & (0xff00u16 >> valid) as u8;
+        let wide = ((byte as u16) << 8) >> shift;
+        if let Some(out) = out.get_mut(i) {
+            *out |= (wide >> 8) as u8;
+        }
+        if let Some(out) = out.get_mut(i + 1) {
+            *out |= wide as u8;
+        }
+    }
+}
+
+/// Arguments of [Command::WriteGRAM] for `len` bytes from `first` on of each buffer row,
+/// starting at `row`
+fn gram_args(row: usize, first: usize, len: usize) -> [u8; 3] {
+    // the visible part of a GRAM column starts 4 pixels in
+    [row as u8, 0x04 + first as u8 * 8, len as u8 * 8 - 1]
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+    use crate::model::{Gp1287Model, NoopDelay, NoopPin};
+    use crate::power::{lit_pixels, PanelPower};
+    use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+    use embedded_graphics::primitives::{Circle, Primitive, PrimitiveStyle};
+    use embedded_graphics::text::Text;
+    use embedded_graphics::Drawable;
+
+    fn scene<D: Display>(display: &mut D) -> Result<(), D::Error> {
+        Circle::new(Point::new(3, 5), 40)
+            .into_styled(PrimitiveStyle::with_stroke(Color::Green, 3))
+            .draw(display)?;
+        display.fill_solid(
+            &Rectangle::new(Point::new(30, 20), Size::new(100, 9)),
+            Color::Green,
+        )?;
+        Text::new(
+            "banded",
+            Point::new(60, 40),
+            MonoTextStyle::new(&FONT_6X10, Color::Green),
+        )
+        .draw(display)?;
+        Ok(())
+    }
+
+    #[test]
+    fn banded_matches_full_frame() {
+        for rotation in [DisplayRotation::Rotate90, DisplayRotation::Rotate180] {
+            let mut full = Display256x50::default();
+            full.set_rotation(rotation);
+            scene(&mut full).unwrap();
+            let mut expected = Gp1287Model::new();
+            VFD256x50::new(&mut expected, NoopPin, NoopDelay)
+                .unwrap()
+                .update_frame(full.buffer())
+                .unwrap();
+            assert!(expected.frame().iter().any(|byte| *byte != 0));
+
+            for (direction, scratch_len) in [
+                (StripDirection::Horizontal, ROW_BYTES * 10),
+                (StripDirection::Horizontal, ROW_BYTES * 256),
+                (StripDirection::Vertical, 256 * 2),
+                (StripDirection::Vertical, 256 * 3 + 5),
+            ] {
+                let mut model = Gp1287Model::new();
+                let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+                let mut scratch = [0xffu8; ROW_BYTES * 256];
+                vfd.update_frame_banded(&mut scratch[..scratch_len], direction, rotation, |d| {
+                    scene(d)
+                })
+                .unwrap();
+                assert_eq!(model.last_error(), None);
+                assert!(model.frame() == expected.frame(), "{:?}", direction);
+            }
+        }
+
+        // no strip fits, nothing is sent
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let mut scratch = [0u8; ROW_BYTES - 1];
+        let result = vfd.update_frame_banded(
+            &mut scratch,
+            StripDirection::Horizontal,
+            DisplayRotation::Rotate0,
+            |_| Ok::<_, ()>(()),
+        );
+        let required = ROW_BYTES;
+        let actual = ROW_BYTES - 1;
+        assert_eq!(
+            result,
+            Err(BandError::Buffer(VarDisplayError::BufferTooSmall {
+                required,
+                actual
+            }))
+        );
+
+        // the error of the second strip stops the frame
+        let mut scratch = [0u8; ROW_BYTES * 128];
+        let mut strips = 0;
+        let result = vfd.update_frame_banded(
+            &mut scratch,
+            StripDirection::Horizontal,
+            DisplayRotation::Rotate0,
+            |d| {
+                strips += 1;
+                d.clear_buffer(Color::Green);
+                if strips == 2 {
+                    return Err("out of glyphs");
+                }
+                Ok(())
+            },
+        );
+        assert_eq!(result, Err(BandError::Draw("out of glyphs")));
+        assert!(model.frame()[..ROW_BYTES * 128].iter().all(|b| *b == 0xff));
+        assert!(model.frame()[ROW_BYTES * 128..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn streamed_frames() {
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.fill_gram(0xa5).unwrap();
+        assert!(model.frame().iter().all(|byte| *byte == 0xa5));
+
+        // a checkerboard of 8x8 squares, generated on the fly
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let pattern = |i: usize| {
+            let (row, byte) = (i / ROW_BYTES, i % ROW_BYTES);
+            if (row / 8 + byte) % 2 == 0 {
+                0xff
+            } else {
+                0x00
+            }
+        };
+        vfd.update_frame_from_iter((0..).map(pattern)).unwrap();
+        assert_eq!(model.last_error(), None);
+        let frame = model.frame();
+        assert!(frame
+            .iter()
+            .enumerate()
+            .all(|(i, byte)| *byte == pattern(i)));
+
+        // a short iterator only overwrites the start
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.fill_gram(0x00).unwrap();
+        vfd.update_frame_from_iter([0xff; ROW_BYTES * 3 + 2])
+            .unwrap();
+        let frame = model.frame();
+        assert!(frame[..ROW_BYTES * 3].iter().all(|byte| *byte == 0xff));
+        assert!(frame[ROW_BYTES * 3..].iter().all(|byte| *byte == 0x00));
+    }
+
+    #[test]
+    fn compressed_frames() {
+        use crate::codec::{encode, FrameStream};
+        let full = [0x5a; NUM_DISPLAY_BITS as usize];
+        let short = [0xff; ROW_BYTES * 3];
+        let mut out = [0u8; 256];
+
+        let len = encode([&full[..]], 1, &mut out).unwrap();
+        let stream = FrameStream::new(&out[..len], full.len()).unwrap();
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.update_frame_compressed(stream.decoder::<1>(0).unwrap())
+            .unwrap();
+        assert!(model.frame() == full);
+
+        let len = encode([&short[..]], 1, &mut out).unwrap();
+        let stream = FrameStream::new(&out[..len], short.len()).unwrap();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        assert_eq!(
+            vfd.update_frame_compressed(stream.decoder::<1>(0).unwrap()),
+            Err(FrameError::Codec(CodecError::LengthMismatch))
+        );
+        assert!(model.frame().iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn transfer_modes_write_same_gram() {
+        let mut full = Display256x50::default();
+        scene(&mut full).unwrap();
+        let modes = [
+            (TransferMode::Single, None),
+            (TransferMode::Chunks(64), None),
+            (TransferMode::Chunks(5), None),
+            (TransferMode::Transactions(100), None),
+            (TransferMode::Transactions(3), None),
+            (TransferMode::Single, Some(50)),
+            (TransferMode::Chunks(16), Some(300)),
+        ];
+        for (mode, dma) in modes {
+            let mut model = Gp1287Model::new();
+            let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+            vfd.set_transfer_mode(mode);
+            // can't hold a buffer row with its command, handed back
+            let small = vfd.set_dma_buffer(std::vec![0u8; ROW_BYTES + 3].leak());
+            assert_eq!(small.map_err(|buffer| buffer.len()), Err(ROW_BYTES + 3));
+            if let Some(len) = dma {
+                vfd.set_dma_buffer(std::vec![0u8; len].leak()).unwrap();
+            }
+            vfd.fill_gram(0x55).unwrap();
+            vfd.update_frame(full.buffer()).unwrap();
+            assert_eq!(model.last_error(), None);
+            assert!(model.frame()[..] == *full.buffer(), "{:?} {:?}", mode, dma);
+
+            let (write, transaction) = match (mode, dma) {
+                (TransferMode::Chunks(size), Some(len)) => (size.max(4), len),
+                // arguments are never split, the longest are 7 bytes
+                (TransferMode::Chunks(size), None) => (size.max(7), usize::MAX),
+                // one buffer row with its command
+                (TransferMode::Transactions(size), _) => {
+                    (size.max(ROW_BYTES), size.max(ROW_BYTES) + 4)
+                }
+                (_, Some(len)) => (len, len),
+                (_, None) => (usize::MAX, usize::MAX),
+            };
+            assert!(model.largest_write() <= write, "{:?} {:?}", mode, dma);
+            assert!(
+                model.largest_transaction() <= transaction,
+                "{:?} {:?}",
+                mode,
+                dma
+            );
+        }
+    }
+
+    #[test]
+    fn lsb_first_bus() {
+        let mut full = Display256x50::default();
+        scene(&mut full).unwrap();
+        for dma in [None, Some(200)] {
+            let mut model = Gp1287Model::with_bit_order(BitOrder::LsbFirst);
+            let mut vfd =
+                VFD256x50::with_bit_order(&mut model, NoopPin, NoopDelay, BitOrder::LsbFirst)
+                    .unwrap();
+            if let Some(len) = dma {
+                vfd.set_dma_buffer(std::vec![0u8; len].leak()).unwrap();
+            }
+            vfd.set_brightness(0x1a5).unwrap();
+            vfd.update_frame(full.buffer()).unwrap();
+            assert_eq!(model.last_error(), None);
+            if dma.is_none() {
+                // still a single transaction for the whole frame
+                assert_eq!(model.largest_transaction(), 1 + 3 + 1792);
+                assert!(model.largest_write() <= 64);
+            }
+            assert_eq!(model.brightness(), 0x1a5);
+            assert_eq!(
+                model.display_area(),
+                [0xFF, 0x31, 0x00, 0x20, 0x00, 0x00, 0x80]
+            );
+            assert!(model.frame()[..] == *full.buffer());
+        }
+
+        // a mismatched bus doesn't even get the commands through
+        let mut model = Gp1287Model::with_bit_order(BitOrder::LsbFirst);
+        assert!(VFD256x50::new(&mut model, NoopPin, NoopDelay).is_err());
+    }
+
+    #[test]
+    fn timing_survives_sleep() {
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.set_scan_speed(ScanSpeed::FAST).unwrap();
+        vfd.set_oscillator(Oscillator::External).unwrap();
+        vfd.sleep().unwrap();
+        vfd.wake_up().unwrap();
+        vfd.init().unwrap();
+        assert_eq!(vfd.scan_speed(), ScanSpeed::FAST);
+        assert_eq!(model.internal_speed(), [0x18, 0x3F, 0x00, 0x01]);
+        assert_eq!(model.oscillation(), Some(0x00));
+
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.wake_up().unwrap();
+        assert_eq!(model.internal_speed(), ScanSpeed::DEFAULT.args());
+        assert_eq!(model.oscillation(), None);
+    }
+
+    #[test]
+    fn synced_present() {
+        let mut full = Display256x50::default();
+        scene(&mut full).unwrap();
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.present(&full, PresentMode::Synced).unwrap();
+        assert_eq!(model.frame_sync(), FRAME_SYNC_ON);
+        assert!(model.frame()[..] == *full.buffer());
+
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.present(&full, PresentMode::Synced).unwrap();
+        vfd.present(&Display256x50::default(), PresentMode::Immediate)
+            .unwrap();
+        assert_eq!(model.frame_sync(), FRAME_SYNC_OFF);
+        assert!(model.frame().iter().all(|byte| *byte == 0));
+
+        // without frame sync, one write per band of the timing model
+        let mut commands = [0; 2];
+        for (i, mode) in [PresentMode::Immediate, PresentMode::Synced]
+            .into_iter()
+            .enumerate()
+        {
+            let mut model = Gp1287Model::new();
+            let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+            vfd.set_frame_sync(FrameSync::Timed(FrameTiming {
+                frame_us: 1000,
+                rows_per_frame: 100,
+            }));
+            vfd.present(&full, mode).unwrap();
+            assert_eq!(model.frame_sync(), FRAME_SYNC_OFF);
+            assert!(model.frame()[..] == *full.buffer());
+            commands[i] = model.commands();
+        }
+        assert_eq!(commands[1] - commands[0], 2);
+    }
+
+    #[test]
+    fn display_modes() {
+        let mut model = Gp1287Model::new();
+        let vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        assert_eq!(vfd.display_mode(), DisplayMode::Normal);
+        for (mode, arg) in [
+            (DisplayMode::AllOn, 0x20),
+            (DisplayMode::AllOff, 0x10),
+            (DisplayMode::Inverted, 0x01),
+            (DisplayMode::Normal, 0x00),
+        ] {
+            let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+            vfd.set_display_mode(mode).unwrap();
+            vfd.init().unwrap();
+            assert_eq!(vfd.display_mode(), mode);
+            assert_eq!(model.display_mode(), arg, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn partial_frames() {
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.fill_gram(0xff).unwrap();
+        // 10 pixels from x = 3 on, the pixels sharing the edge bytes are cleared
+        let lines = [0b1011_0011, 0b0111_1111, 0b1100_0000, 0b0011_1111];
+        vfd.update_partial_frame(&lines, 3, 5, 10, 2).unwrap();
+        // clipped to the right edge of the buffer rows
+        vfd.update_partial_frame(&[0xff, 0xff], 52, 7, 16, 1)
+            .unwrap();
+        // empty blocks write nothing
+        vfd.update_partial_frame(&[0x00; 4], 3, 9, 0, 2).unwrap();
+        vfd.update_partial_frame(&[0x00; 4], 3, 9, 8, 0).unwrap();
+        assert_eq!(model.last_error(), None);
+        let frame = model.frame();
+        assert_eq!(
+            frame[5 * ROW_BYTES..5 * ROW_BYTES + 3],
+            [0b0001_0110, 0b0110_1000, 0xff]
+        );
+        assert_eq!(
+            frame[6 * ROW_BYTES..6 * ROW_BYTES + 3],
+            [0b0001_1000, 0b0000_0000, 0xff]
+        );
+        assert_eq!(frame[4 * ROW_BYTES..5 * ROW_BYTES], [0xff; ROW_BYTES]);
+        assert_eq!(frame[7 * ROW_BYTES + 5..8 * ROW_BYTES], [0xff, 0x0f]);
+        assert_eq!(frame[9 * ROW_BYTES..11 * ROW_BYTES], [0xff; 2 * ROW_BYTES]);
+
+        // whole buffer rows go out in a single write
+        let mut model = Gp1287Model::new();
+        VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let init = model.commands();
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.update_partial_frame(&[0x3c; ROW_BYTES * 40], 0, 100, WIDTH, 40)
+            .unwrap();
+        assert_eq!(model.commands() - init, 1);
+        assert!(model.frame()[100 * ROW_BYTES..140 * ROW_BYTES]
+            .iter()
+            .all(|byte| *byte == 0x3c));
+    }
+
+    #[test]
+    fn reduced_display_area() {
+        let mut full = Display256x50::default();
+        scene(&mut full).unwrap();
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let area = Rectangle::new(Point::new(10, 100), Size::new(20, 40));
+        vfd.set_display_area(area, DisplayRotation::Rotate0)
+            .unwrap();
+        // widened to bytes 1..4
+        let physical = Rectangle::new(Point::new(8, 100), Size::new(24, 40));
+        assert_eq!(vfd.display_area(), physical);
+        vfd.update_frame(full.buffer()).unwrap();
+        vfd.update_partial_frame(&[0xff; 4], 16, 120, 16, 2)
+            .unwrap();
+        assert_eq!(model.display_area(), [39, 23, 100, 0x20, 8, 0x00, 0x80]);
+        // GRAM column 0 and row 4 hold the top left corner of the area
+        for column in 0..HEIGHT as usize {
+            for row in 4..64 {
+                let point = Point::new(row as i32 + 4, column as i32 + 100);
+                let expected = if (120..122).contains(&point.y) && (16..32).contains(&point.x) {
+                    true
+                } else if physical.contains(point) {
+                    let byte = full.buffer()[point.y as usize * ROW_BYTES + point.x as usize / 8];
+                    byte & (0x80 >> (point.x % 8)) != 0
+                } else {
+                    false
+                };
+                assert_eq!(model.pixel(column, row), Some(expected), "{:?}", point);
+            }
+        }
+
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.set_display_area(
+            Rectangle::new(Point::new(0, 60), Size::new(8, 8)),
+            DisplayRotation::Rotate0,
+        )
+        .unwrap();
+        vfd.reset_display_area().unwrap();
+        vfd.update_frame(full.buffer()).unwrap();
+        assert_eq!(
+            model.display_area(),
+            [0xFF, 0x31, 0x00, 0x20, 0x00, 0x00, 0x80]
+        );
+        assert!(model.frame()[..] == *full.buffer());
+    }
+
+    #[test]
+    fn power_limit() {
+        let limit = PowerLimit {
+            panel: PanelPower {
+                base_ua: 90_000,
+                scan_ua: 40_000,
+                pixel_na: 10_000,
+            },
+            budget_ma: 150,
+        };
+        let mut lit = Display256x50::default();
+        lit.clear_buffer(Color::Green);
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        vfd.set_brightness(0x300).unwrap();
+        vfd.set_power_limit(Some(limit)).unwrap();
+        assert_eq!(vfd.applied_brightness(), 0x300);
+
+        vfd.update_frame(lit.buffer()).unwrap();
+        let capped = vfd.applied_brightness();
+        // the unconnected columns don't count
+        assert_eq!(capped, limit.panel.max_brightness(50 * 256, 150));
+        assert_eq!(lit_pixels(&lit), 50 * 256);
+        assert!(limit.panel.estimate(&lit, capped) <= 150);
+        assert_eq!(vfd.brightness(), 0x300);
+        vfd.set_brightness(0x3ff).unwrap();
+        assert_eq!(vfd.applied_brightness(), capped);
+
+        vfd.present_buffer(&[0; NUM_DISPLAY_BITS as usize], PresentMode::Immediate)
+            .unwrap();
+        assert_eq!(vfd.applied_brightness(), 0x3ff);
+        // every other write and the display mode count as well
+        vfd.fill_gram(0xff).unwrap();
+        assert_eq!(vfd.applied_brightness(), capped);
+        vfd.set_display_mode(DisplayMode::Inverted).unwrap();
+        assert_eq!(vfd.applied_brightness(), 0x3ff);
+        vfd.set_display_mode(DisplayMode::Normal).unwrap();
+        vfd.update_partial_frame(&[0; ROW_BYTES * 128], 0, 0, WIDTH, 128)
+            .unwrap();
+        let half = vfd.applied_brightness();
+        assert_eq!(half, limit.panel.max_brightness(50 * 128, 150));
+        vfd.update_frame_from_iter(core::iter::repeat(0xff))
+            .unwrap();
+        assert_eq!(vfd.applied_brightness(), capped);
+        vfd.clear_frame().unwrap();
+        assert_eq!(vfd.applied_brightness(), 0x3ff);
+
+        vfd.update_frame(lit.buffer()).unwrap();
+        vfd.set_power_limit(None).unwrap();
+        assert_eq!(model.brightness(), 0x3ff);
+    }
+}
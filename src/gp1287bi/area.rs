@@ -0,0 +1,84 @@
+//! Active display area of the GP1287
+//!
+//! Grids outside of the area are not scanned, which saves filament and anode power.
+//! The controller reads the area from the start of the GRAM, so writes have to be remapped
+//! while the area is reduced.
+//!
+//! Unverified: there is no datasheet reference for reading the area from the start of the
+//! GRAM, nor for the start arguments of [Area::args]. The model doesn't simulate the area
+//! either, so the tests only check the remapping. Check it on the module.
+
+use super::{HEIGHT, ROW_BYTES};
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+
+/// Pixel rows of the glass, the last bits of each buffer row are not connected
+pub(super) const GLASS_ROWS: usize = 50;
+
+/// Active area in buffer rows, which are the grids, and bytes of each buffer row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Area {
+    pub(crate) first_row: usize,
+    pub(crate) rows: usize,
+    pub(crate) first_byte: usize,
+    pub(crate) bytes: usize,
+}
+
+impl Area {
+    /// The whole glass
+    pub(crate) const FULL: Area = Area {
+        first_row: 0,
+        rows: HEIGHT as usize,
+        first_byte: 0,
+        bytes: ROW_BYTES,
+    };
+
+    /// Area covering `area` of the unrotated buffer, widened to whole bytes
+    ///
+    /// Returns None if no visible pixel is left.
+    #[cfg(feature = "graphics")]
+    pub(crate) fn covering(area: &Rectangle) -> Option<Area> {
+        let glass = Rectangle::new(Point::zero(), Size::new(GLASS_ROWS as u32, HEIGHT));
+        let area = area.intersection(&glass);
+        let bottom_right = area.bottom_right()?;
+        let first_byte = area.top_left.x as usize / 8;
+        Some(Area {
+            first_row: area.top_left.y as usize,
+            rows: area.size.height as usize,
+            first_byte,
+            bytes: bottom_right.x as usize / 8 + 1 - first_byte,
+        })
+    }
+
+    /// Arguments of the display area setting
+    pub(crate) fn args(self) -> [u8; 7] {
+        let pixels = (self.bytes * 8).min(GLASS_ROWS - self.first_byte * 8);
+        [
+            (self.rows - 1) as u8,
+            (pixels - 1) as u8,
+            self.first_row as u8,
+            0x20,
+            (self.first_byte * 8) as u8,
+            0x00,
+            0x80,
+        ]
+    }
+
+    /// Clips `rows` buffer rows from `row` on and `bytes` bytes from `byte` on to the area
+    ///
+    /// Returns the clipped ranges, or None if nothing is left.
+    pub(crate) fn clip(
+        self,
+        row: usize,
+        rows: usize,
+        byte: usize,
+        bytes: usize,
+    ) -> Option<(core::ops::Range<usize>, core::ops::Range<usize>)> {
+        let rows = row.max(self.first_row)..(row + rows).min(self.first_row + self.rows);
+        let bytes = byte.max(self.first_byte)..(byte + bytes).min(self.first_byte + self.bytes);
+        if rows.is_empty() || bytes.is_empty() {
+            return None;
+        }
+        Some((rows, bytes))
+    }
+}
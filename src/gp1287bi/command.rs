@@ -2,7 +2,7 @@
 use crate::traits;
 
 #[allow(dead_code)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub(crate) enum Command {
     // software reset
     Reset = 0b1010_1010,
@@ -25,6 +25,35 @@ pub(crate) enum Command {
     Sleep = 0b0110_0001,
 }
 
+impl Command {
+    const ALL: [Command; 15] = [
+        Command::Reset,
+        Command::ClearGRAM,
+        Command::VFDModeSetting,
+        Command::DisplayAreaSetting,
+        Command::InternalSpeedSetting,
+        Command::BrightnessSetting,
+        Command::WriteGRAM,
+        Command::DisplayPosition1Offset,
+        Command::DisplayPosition2Offset,
+        Command::DisplayModeSetting,
+        Command::FrameSyncSetting,
+        Command::OscillationSetting,
+        Command::UnknownInit,
+        Command::WakeUp,
+        Command::Sleep,
+    ];
+
+    /// Looks up the command with the given address
+    #[allow(dead_code)]
+    pub(crate) fn from_address(address: u8) -> Option<Command> {
+        Command::ALL
+            .iter()
+            .copied()
+            .find(|command| *command as u8 == address)
+    }
+}
+
 impl traits::Command for Command {
     /// Returns the address of the command
     fn address(self) -> u8 {
@@ -0,0 +1,29 @@
+//! Hardware display modes of the GP1287
+
+/// What the controller shows, independent of the GRAM contents
+///
+/// Switching modes needs no GRAM traffic, e.g. for flashing an alert or a quick factory test.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// The GRAM as it is
+    #[default]
+    Normal,
+    /// Every pixel lit
+    AllOn,
+    /// Every pixel dark
+    AllOff,
+    /// The GRAM with lit and dark pixels swapped
+    Inverted,
+}
+
+impl DisplayMode {
+    /// Argument of the display mode setting
+    pub(crate) fn arg(self) -> u8 {
+        match self {
+            DisplayMode::Normal => 0x00,
+            DisplayMode::AllOn => 0x20,
+            DisplayMode::AllOff => 0x10,
+            DisplayMode::Inverted => 0x01,
+        }
+    }
+}
@@ -1,26 +1,59 @@
 use crate::gp1287bi::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
-use crate::graphics::{Display, DisplayRotation};
+use crate::graphics::{
+    ascii_art, clip_physical, layout_draw, layout_pixel, physical_area, Display, DisplayRotation,
+    LayoutDisplay, PixelLayout, RowMajor, VarDisplay, VarDisplayError,
+};
 use crate::prelude::Color;
+use core::marker::PhantomData;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Full size buffer for use with the 1in54c EPD
 ///
 /// Can also be manually constructed and be used together with VarDisplay
-pub struct Display256x50 {
+///
+/// The buffer is packed like the GP1287 GRAM, other panels of the same size can use
+/// another [PixelLayout] with [Display256x50::with_layout].
+pub struct Display256x50<L = RowMajor> {
     buffer: [u8; NUM_DISPLAY_BITS as usize],
     rotation: DisplayRotation,
+    layout: PhantomData<L>,
 }
 
 impl Default for Display256x50 {
     fn default() -> Self {
+        Self::with_layout()
+    }
+}
+
+impl<L: PixelLayout> Display256x50<L> {
+    /// Creates an empty display with the buffer packed according to `L`
+    pub fn with_layout() -> Self {
         Display256x50 {
             buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); NUM_DISPLAY_BITS as usize],
             rotation: DisplayRotation::default(),
+            layout: PhantomData,
         }
     }
+
+    /// Returns a display drawing only into `area` of this one, e.g. for a status bar
+    ///
+    /// `area` is given in the current rotation, which the window inherits.
+    pub fn window(&mut self, area: Rectangle) -> Result<VarDisplay<'_, L>, VarDisplayError> {
+        let physical = physical_area(&area, WIDTH, HEIGHT, self.rotation)
+            .ok_or(VarDisplayError::OutOfBounds)?;
+        let mut window = VarDisplay::window_with_layout(
+            &mut self.buffer,
+            L::stride(WIDTH, HEIGHT),
+            physical.top_left,
+            physical.size,
+        )?;
+        window.set_rotation(self.rotation);
+        Ok(window)
+    }
 }
 
-impl DrawTarget for Display256x50 {
+impl<L: PixelLayout> DrawTarget for Display256x50<L> {
     type Color = Color;
     type Error = core::convert::Infallible;
 
@@ -33,15 +66,23 @@ impl DrawTarget for Display256x50 {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let size = Size::new(WIDTH, HEIGHT);
+        if let Some(area) = clip_physical(area, size, self.rotation) {
+            L::fill(&mut self.buffer, L::stride(WIDTH, HEIGHT), &area, color);
+        }
+        Ok(())
+    }
 }
 
-impl OriginDimensions for Display256x50 {
+impl<L> OriginDimensions for Display256x50<L> {
     fn size(&self) -> Size {
         Size::new(WIDTH, HEIGHT)
     }
 }
 
-impl Display for Display256x50 {
+impl<L: PixelLayout> Display for Display256x50<L> {
     fn buffer(&self) -> &[u8] {
         &self.buffer
     }
@@ -57,4 +98,27 @@ impl Display for Display256x50 {
     fn rotation(&self) -> DisplayRotation {
         self.rotation
     }
+
+    fn get_pixel(&self, point: Point) -> Option<Color> {
+        layout_pixel::<L, Self>(self, point)
+    }
+
+    fn draw_helper(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixel: Pixel<Color>,
+    ) -> Result<(), Self::Error> {
+        layout_draw::<L, Self>(self, width, height, pixel)
+    }
+}
+
+impl<L: PixelLayout> LayoutDisplay for Display256x50<L> {
+    type Layout = L;
+}
+
+impl<L: PixelLayout> core::fmt::Debug for Display256x50<L> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        ascii_art(self, f)
+    }
 }
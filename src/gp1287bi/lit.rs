@@ -0,0 +1,124 @@
+//! Lit pixels of the GRAM, for the power limit
+//!
+//! The GRAM can't be read back, so the driver counts the set bits of each byte as it writes
+//! them. Only the glass inside the display area is counted, with the display mode applied.
+
+use super::area::{Area, GLASS_ROWS};
+use super::{DisplayMode, HEIGHT, NUM_DISPLAY_BITS, ROW_BYTES};
+
+/// Set bits on the glass of each GRAM byte, in buffer coordinates
+pub(crate) struct LitPixels {
+    /// two counts per byte, the even index in the low nibble
+    counts: [u8; NUM_DISPLAY_BITS as usize / 2],
+}
+
+impl LitPixels {
+    /// A cleared GRAM
+    pub(crate) const DARK: LitPixels = LitPixels {
+        counts: [0; NUM_DISPLAY_BITS as usize / 2],
+    };
+
+    /// Records `len` bytes of each buffer row from `first` on, starting at `row`
+    pub(crate) fn write(&mut self, row: usize, first: usize, len: usize, data: &[u8]) {
+        for (i, line) in data.chunks(len.max(1)).enumerate() {
+            if row + i >= HEIGHT as usize {
+                break;
+            }
+            for (byte, value) in (first..ROW_BYTES).zip(line) {
+                self.set((row + i) * ROW_BYTES + byte, on_glass(byte, *value));
+            }
+        }
+    }
+
+    /// Records every byte of the GRAM set to `value`
+    pub(crate) fn fill(&mut self, value: u8) {
+        for index in 0..NUM_DISPLAY_BITS as usize {
+            self.set(index, on_glass(index % ROW_BYTES, value));
+        }
+    }
+
+    /// Lit pixels inside `area` as shown in `mode`
+    pub(crate) fn count(&self, mode: DisplayMode, area: Area) -> u32 {
+        count(mode, area, |index| self.get(index))
+    }
+
+    fn get(&self, index: usize) -> u32 {
+        (self.counts[index / 2] >> (index % 2 * 4)) as u32 & 0x0f
+    }
+
+    fn set(&mut self, index: usize, count: u32) {
+        let shift = index % 2 * 4;
+        let counts = &mut self.counts[index / 2];
+        *counts = (*counts & !(0x0f << shift)) | ((count as u8) << shift);
+    }
+}
+
+/// Lit pixels inside `area` in `mode` once the whole `frame` is written
+pub(crate) fn frame_lit(frame: &[u8], mode: DisplayMode, area: Area) -> u32 {
+    count(mode, area, |index| {
+        frame
+            .get(index)
+            .map_or(0, |value| on_glass(index % ROW_BYTES, *value))
+    })
+}
+
+/// Lit pixels on the glass of the whole `frame`, shown normally
+#[cfg(feature = "graphics")]
+pub(crate) fn glass_lit(frame: &[u8]) -> u32 {
+    frame_lit(frame, DisplayMode::Normal, Area::FULL)
+}
+
+// lit pixels of the area in `mode`, from the lit pixels of each byte
+fn count(mode: DisplayMode, area: Area, lit: impl Fn(usize) -> u32) -> u32 {
+    let (mut visible, mut on) = (0, 0);
+    for row in area.first_row..area.first_row + area.rows {
+        for byte in area.first_byte..area.first_byte + area.bytes {
+            visible += on_glass(byte, 0xff);
+            on += lit(row * ROW_BYTES + byte);
+        }
+    }
+    match mode {
+        DisplayMode::Normal => on,
+        DisplayMode::AllOn => visible,
+        DisplayMode::AllOff => 0,
+        DisplayMode::Inverted => visible - on,
+    }
+}
+
+// set bits of `value` which are on the glass, for byte `byte` of a buffer row
+fn on_glass(byte: usize, value: u8) -> u32 {
+    let bits = GLASS_ROWS.saturating_sub(byte * 8).min(8);
+    (value & (0xff00u16 >> bits) as u8).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_glass() {
+        let mut lit = LitPixels::DARK;
+        lit.fill(0xff);
+        assert_eq!(lit.count(DisplayMode::Normal, Area::FULL), 50 * 256);
+        lit.write(10, 5, 2, &[0x00, 0xff, 0x0f, 0x0f]);
+        assert_eq!(lit.count(DisplayMode::Normal, Area::FULL), 50 * 256 - 8 - 6);
+        assert_eq!(lit.count(DisplayMode::Inverted, Area::FULL), 8 + 6);
+        assert_eq!(lit.count(DisplayMode::AllOn, Area::FULL), 50 * 256);
+        assert_eq!(lit.count(DisplayMode::AllOff, Area::FULL), 0);
+
+        let area = Area {
+            first_row: 10,
+            rows: 2,
+            first_byte: 5,
+            bytes: 2,
+        };
+        assert_eq!(lit.count(DisplayMode::Normal, area), 2 + 4);
+        assert_eq!(lit.count(DisplayMode::AllOn, area), 2 * 10);
+        let frame = [0xffu8; NUM_DISPLAY_BITS as usize];
+        assert_eq!(frame_lit(&frame, DisplayMode::Inverted, area), 0);
+        assert_eq!(
+            frame_lit(&frame[..100], DisplayMode::Normal, Area::FULL),
+            100 * 8 - 14 * 6
+        );
+    }
+}
@@ -0,0 +1,487 @@
+//! Recovering from a scrambled controller
+//!
+//! ESD and supply glitches can leave the GP1287 with garbage in its registers or GRAM, which
+//! a power cycle used to be the only fix for. [Resilient] keeps the last frame next to the
+//! settings the driver already shadows. After a failed write, or every refresh interval,
+//! it resets and initializes the controller again and restores both.
+//!
+//! ```ignore
+//! let mut vfd = Resilient::new(VFD256x50::new(spi, rst, delay)?)
+//!     .policy(RetryPolicy { max_retries: 5, ..RetryPolicy::default() })
+//!     .refresh_interval(60_000);
+//! vfd.update_frame(display.buffer())?;
+//! loop {
+//!     vfd.poll(millis())?;
+//! }
+//! ```
+
+use embedded_hal::{delay::DelayNs, digital::*, spi::SpiDevice};
+
+use super::{shift_line, PartialBlock, PresentMode, VFD256x50, NUM_DISPLAY_BITS, ROW_BYTES};
+use crate::codec::{CodecError, FrameDecoder, FrameError};
+#[cfg(feature = "graphics")]
+use crate::graphics::Display;
+use crate::traits::EEIDisplay;
+
+/// How often and how patiently a failed operation is retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Recoveries tried before the error is returned, 0 disables them
+    pub max_retries: u32,
+    /// Wait before the first recovery, in ms
+    pub backoff_ms: u32,
+    /// Each further wait is this many times longer
+    pub backoff_factor: u32,
+    /// Longest wait, in ms
+    pub max_backoff_ms: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            backoff_ms: 10,
+            backoff_factor: 2,
+            max_backoff_ms: 1000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before recovery `attempt`, counting from 0, in ms
+    pub fn backoff(&self, attempt: u32) -> u32 {
+        let factor = self.backoff_factor.saturating_pow(attempt);
+        self.backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms)
+    }
+}
+
+/// [VFD256x50] which recovers from errors by initializing the controller again
+///
+/// Settings changed through [Resilient::run] are restored after each recovery, because the
+/// driver keeps them itself: brightness, power limit, oscillator, scan speed, display mode
+/// and display area. The frame is restored if it was written through the methods of
+/// [Resilient] which save it, e.g. [Resilient::update_frame] or [Resilient::fill_gram].
+/// A GRAM write through [Resilient::run] drops the saved frame, later recoveries leave the
+/// GRAM cleared until a frame is saved again.
+pub struct Resilient<SPI, RST, DELAY> {
+    vfd: VFD256x50<SPI, RST, DELAY>,
+    frame: [u8; NUM_DISPLAY_BITS as usize],
+    /// frame holds what should be in the GRAM
+    has_frame: bool,
+    policy: RetryPolicy,
+    refresh_interval: Option<u32>,
+    /// timestamp of the next refresh
+    due: Option<u32>,
+    recoveries: u32,
+}
+
+impl<SPI, RST, DELAY> Resilient<SPI, RST, DELAY>
+where
+    SPI: SpiDevice,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Wraps an initialized driver, with the default [RetryPolicy] and no periodic refresh
+    pub fn new(vfd: VFD256x50<SPI, RST, DELAY>) -> Self {
+        Resilient {
+            vfd,
+            frame: [0; NUM_DISPLAY_BITS as usize],
+            has_frame: false,
+            policy: RetryPolicy::default(),
+            refresh_interval: None,
+            due: None,
+            recoveries: 0,
+        }
+    }
+
+    /// Sets how failed operations are retried
+    pub fn policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Restores the controller every `ms` milliseconds from [Resilient::poll] on
+    pub fn refresh_interval(mut self, ms: u32) -> Self {
+        self.refresh_interval = Some(ms);
+        self
+    }
+
+    /// Returns the driver
+    pub fn vfd(&self) -> &VFD256x50<SPI, RST, DELAY> {
+        &self.vfd
+    }
+
+    /// Returns the driver, dropping the saved frame
+    pub fn into_inner(self) -> VFD256x50<SPI, RST, DELAY> {
+        self.vfd
+    }
+
+    /// Number of recoveries so far, periodic refreshes included
+    pub fn recoveries(&self) -> u32 {
+        self.recoveries
+    }
+
+    /// Runs `op` on the driver, recovering and retrying it if it fails
+    ///
+    /// `op` may run several times, so it should only set state, e.g. a setting of the driver.
+    /// If it writes to the GRAM, the saved frame is dropped.
+    pub fn run<T, F>(&mut self, mut op: F) -> Result<T, SPI::Error>
+    where
+        F: FnMut(&mut VFD256x50<SPI, RST, DELAY>) -> Result<T, SPI::Error>,
+    {
+        let mut wrote = false;
+        let result = self.attempt(|vfd| {
+            let writes = vfd.gram_writes();
+            let result = op(vfd);
+            wrote |= vfd.gram_writes() != writes;
+            result
+        });
+        if wrote {
+            self.has_frame = false;
+        }
+        result
+    }
+
+    /// Like [EEIDisplay::update_frame], saving the frame for later recoveries
+    ///
+    /// Buffers of another length than the display are rejected with
+    /// [CodecError::LengthMismatch] before anything is sent.
+    pub fn update_frame(&mut self, buffer: &[u8]) -> Result<(), FrameError<SPI::Error>> {
+        self.save(buffer)?;
+        self.send(|vfd, frame| vfd.update_frame(frame))
+            .map_err(FrameError::Spi)
+    }
+
+    /// Like [VFD256x50::present_buffer], saving the frame for later recoveries
+    ///
+    /// Buffers of another length than the display are rejected like by
+    /// [Resilient::update_frame].
+    pub fn present_buffer(
+        &mut self,
+        buffer: &[u8],
+        mode: PresentMode,
+    ) -> Result<(), FrameError<SPI::Error>> {
+        self.save(buffer)?;
+        self.send(|vfd, frame| vfd.present_buffer(frame, mode))
+            .map_err(FrameError::Spi)
+    }
+
+    /// Like [VFD256x50::present], saving the frame for later recoveries
+    #[cfg(feature = "graphics")]
+    pub fn present<D: Display>(
+        &mut self,
+        display: &D,
+        mode: PresentMode,
+    ) -> Result<(), FrameError<SPI::Error>> {
+        self.present_buffer(display.buffer(), mode)
+    }
+
+    /// Like [VFD256x50::fill_gram], saving the frame for later recoveries
+    pub fn fill_gram(&mut self, byte: u8) -> Result<(), SPI::Error> {
+        self.frame.fill(byte);
+        self.has_frame = true;
+        self.send(|vfd, _| vfd.fill_gram(byte))
+    }
+
+    /// Like [VFD256x50::update_frame_from_iter], saving the written rows for later recoveries
+    ///
+    /// The rows after the end of the iterator are restored as saved before, or dark if no
+    /// frame was saved.
+    pub fn update_frame_from_iter<I>(&mut self, bytes: I) -> Result<(), SPI::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut bytes = bytes.into_iter();
+        let mut len = 0;
+        for row in self.frame.chunks_exact_mut(ROW_BYTES) {
+            let mut line = [0; ROW_BYTES];
+            let mut count = 0;
+            for (slot, byte) in line.iter_mut().zip(&mut bytes) {
+                *slot = byte;
+                count += 1;
+            }
+            if count < ROW_BYTES {
+                // a trailing partial row is dropped
+                break;
+            }
+            row.copy_from_slice(&line);
+            len += ROW_BYTES;
+        }
+        if !self.has_frame {
+            self.frame[len..].fill(0);
+            self.has_frame = true;
+        }
+        self.send(|vfd, frame| vfd.update_frame_from_iter(frame[..len].iter().copied()))
+    }
+
+    /// Like [VFD256x50::update_frame_compressed], saving the frame for later recoveries
+    pub fn update_frame_compressed<const DEPTH: usize>(
+        &mut self,
+        frame: FrameDecoder<'_, DEPTH>,
+    ) -> Result<(), FrameError<SPI::Error>> {
+        if frame.remaining() != NUM_DISPLAY_BITS as usize {
+            return Err(CodecError::LengthMismatch.into());
+        }
+        self.update_frame_from_iter(frame).map_err(FrameError::Spi)
+    }
+
+    /// Like [EEIDisplay::update_partial_frame], saving the changes for later recoveries
+    pub fn update_partial_frame(
+        &mut self,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), SPI::Error> {
+        if !self.has_frame {
+            self.frame.fill(0);
+        }
+        if let Some(block) = PartialBlock::new(buffer.len(), x, y, width, height) {
+            for i in 0..block.rows {
+                let start = (block.row + i) * ROW_BYTES + block.first;
+                let out = &mut self.frame[start..start + block.len];
+                shift_line(block.line(buffer, i), width, block.shift, out);
+            }
+        }
+        self.has_frame = true;
+        self.attempt(|vfd| vfd.update_partial_frame(buffer, x, y, width, height))
+    }
+
+    /// Resets the controller and restores settings and frame right away
+    pub fn recover(&mut self) -> Result<(), SPI::Error> {
+        let result = self.restore();
+        self.retry(result, |_| Ok(()))
+    }
+
+    /// Restores the controller if the refresh interval has passed
+    ///
+    /// `now` is a millisecond timestamp which may wrap around. Returns true if the
+    /// controller was restored.
+    pub fn poll(&mut self, now: u32) -> Result<bool, SPI::Error> {
+        let Some(interval) = self.refresh_interval else {
+            return Ok(false);
+        };
+        match self.due {
+            // wrapping comparison, due is still in the future
+            Some(due) if (now.wrapping_sub(due) as i32) < 0 => Ok(false),
+            Some(_) => {
+                self.due = Some(now.wrapping_add(interval));
+                self.recover()?;
+                Ok(true)
+            }
+            None => {
+                self.due = Some(now.wrapping_add(interval));
+                Ok(false)
+            }
+        }
+    }
+
+    // runs `op`, recovering and retrying it if it fails
+    fn attempt<T, F>(&mut self, mut op: F) -> Result<T, SPI::Error>
+    where
+        F: FnMut(&mut VFD256x50<SPI, RST, DELAY>) -> Result<T, SPI::Error>,
+    {
+        let result = op(&mut self.vfd);
+        self.retry(result, op)
+    }
+
+    // copies a whole frame into the saved frame
+    fn save(&mut self, buffer: &[u8]) -> Result<(), CodecError> {
+        if buffer.len() != self.frame.len() {
+            return Err(CodecError::LengthMismatch);
+        }
+        self.frame.copy_from_slice(buffer);
+        self.has_frame = true;
+        Ok(())
+    }
+
+    // sends the saved frame once, a recovery restores it in full
+    fn send<F>(&mut self, op: F) -> Result<(), SPI::Error>
+    where
+        F: FnOnce(
+            &mut VFD256x50<SPI, RST, DELAY>,
+            &[u8; NUM_DISPLAY_BITS as usize],
+        ) -> Result<(), SPI::Error>,
+    {
+        let result = op(&mut self.vfd, &self.frame);
+        self.retry(result, |_| Ok(()))
+    }
+
+    // recovers and runs `op` again until it succeeds or the policy gives up
+    fn retry<T, F>(&mut self, mut result: Result<T, SPI::Error>, mut op: F) -> Result<T, SPI::Error>
+    where
+        F: FnMut(&mut VFD256x50<SPI, RST, DELAY>) -> Result<T, SPI::Error>,
+    {
+        let mut attempt = 0;
+        while let Err(e) = result {
+            if attempt >= self.policy.max_retries {
+                return Err(e);
+            }
+            self.vfd.delay_ms(self.policy.backoff(attempt));
+            attempt += 1;
+            // a failed recovery counts as a failed attempt
+            result = self.restore().and_then(|_| op(&mut self.vfd));
+        }
+        result
+    }
+
+    // hard reset, init, shadowed settings and the saved frame
+    fn restore(&mut self) -> Result<(), SPI::Error> {
+        self.recoveries += 1;
+        self.vfd.reinit()?;
+        if self.has_frame {
+            self.vfd.update_frame(&self.frame)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gp1287bi::{DisplayMode, ScanSpeed};
+    use crate::model::{Gp1287Model, ModelError, NoopDelay, NoopPin};
+    use core::cell::Cell;
+    use embedded_hal::spi::{ErrorType, Operation};
+
+    // fails the next `failures` transactions
+    struct Flaky<'a> {
+        model: &'a mut Gp1287Model,
+        failures: &'a Cell<u32>,
+    }
+
+    impl ErrorType for Flaky<'_> {
+        type Error = ModelError;
+    }
+
+    impl SpiDevice for Flaky<'_> {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ModelError> {
+            match self.failures.get() {
+                0 => self.model.transaction(operations),
+                n => {
+                    self.failures.set(n - 1);
+                    Err(ModelError::UnsupportedOperation)
+                }
+            }
+        }
+    }
+
+    fn frame() -> [u8; NUM_DISPLAY_BITS as usize] {
+        core::array::from_fn(|i| (i * 7) as u8)
+    }
+
+    #[test]
+    fn restores_after_glitch() {
+        let mut model = Gp1287Model::new();
+        let vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let mut vfd = Resilient::new(vfd);
+        vfd.run(|vfd| vfd.set_brightness(0x1ff)).unwrap();
+        vfd.run(|vfd| vfd.set_scan_speed(ScanSpeed::SLOW)).unwrap();
+        vfd.run(|vfd| vfd.set_display_mode(DisplayMode::Inverted))
+            .unwrap();
+        vfd.update_frame(&frame()).unwrap();
+        vfd.update_partial_frame(&[0xff; 14], 0, 3, 56, 2).unwrap();
+
+        // the controller dropped into sleep, the next write fails and recovers it
+        vfd.run(|vfd| vfd.sleep()).unwrap();
+        vfd.update_partial_frame(&[0xaa; 7], 0, 9, 56, 1).unwrap();
+        assert_eq!(vfd.recoveries(), 1);
+        vfd.update_partial_frame(&[0xff, 0xf0], 4, 12, 12, 1)
+            .unwrap();
+        vfd.recover().unwrap();
+
+        let mut expected = frame();
+        expected[3 * ROW_BYTES..5 * ROW_BYTES].fill(0xff);
+        expected[9 * ROW_BYTES..10 * ROW_BYTES].fill(0xaa);
+        expected[12 * ROW_BYTES..12 * ROW_BYTES + 2].copy_from_slice(&[0x0f, 0xff]);
+        assert!(!model.is_asleep());
+        assert!(model.frame() == expected);
+        assert_eq!(model.brightness(), 0x1ff);
+        assert_eq!(model.internal_speed(), ScanSpeed::SLOW.args());
+        assert_eq!(model.display_mode(), DisplayMode::Inverted.arg());
+    }
+
+    #[test]
+    fn saves_gram_writes() {
+        let mut model = Gp1287Model::new();
+        let vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let mut vfd = Resilient::new(vfd);
+        assert_eq!(
+            vfd.update_frame(&[0xff; 100]),
+            Err(FrameError::Codec(CodecError::LengthMismatch))
+        );
+        vfd.fill_gram(0x81).unwrap();
+        vfd.update_frame_from_iter([0x3c; 2 * ROW_BYTES + 3])
+            .unwrap();
+        vfd.recover().unwrap();
+        vfd.present_buffer(&frame(), PresentMode::Synced).unwrap();
+
+        // written behind the back of the saved frame
+        vfd.run(|vfd| vfd.fill_gram(0x42)).unwrap();
+        vfd.run(|vfd| vfd.set_brightness(0x80)).unwrap();
+        vfd.recover().unwrap();
+        assert!(model.frame().iter().all(|byte| *byte == 0));
+
+        // a block written afterwards isn't restored onto the dropped frame
+        let mut model = Gp1287Model::new();
+        let vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let mut vfd = Resilient::new(vfd);
+        vfd.update_frame(&frame()).unwrap();
+        vfd.run(|vfd| vfd.clear_frame()).unwrap();
+        vfd.update_partial_frame(&[0xff; 7], 0, 4, 56, 1).unwrap();
+        vfd.recover().unwrap();
+        let mut expected = [0; NUM_DISPLAY_BITS as usize];
+        expected[4 * ROW_BYTES..5 * ROW_BYTES].fill(0xff);
+        assert!(model.frame() == expected);
+
+        let mut model = Gp1287Model::new();
+        let vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let mut vfd = Resilient::new(vfd);
+        vfd.fill_gram(0x81).unwrap();
+        vfd.update_frame_from_iter([0x3c; 2 * ROW_BYTES + 3])
+            .unwrap();
+        vfd.recover().unwrap();
+        let mut expected = [0x81; NUM_DISPLAY_BITS as usize];
+        expected[..2 * ROW_BYTES].fill(0x3c);
+        assert!(model.frame() == expected);
+    }
+
+    #[test]
+    fn retries_and_gives_up() {
+        let mut model = Gp1287Model::new();
+        let failures = Cell::new(0);
+        let spi = Flaky {
+            model: &mut model,
+            failures: &failures,
+        };
+        let vfd = VFD256x50::new(spi, NoopPin, NoopDelay).unwrap();
+        let mut vfd = Resilient::new(vfd).refresh_interval(100);
+        vfd.update_frame(&frame()).unwrap();
+
+        // fails the write and the first recovery
+        failures.set(2);
+        vfd.run(|vfd| vfd.set_brightness(0x80)).unwrap();
+        assert_eq!(vfd.recoveries(), 2);
+
+        failures.set(u32::MAX);
+        assert!(vfd.update_frame(&[0; 1792]).is_err());
+        assert_eq!(vfd.recoveries(), 2 + 3);
+        failures.set(0);
+
+        assert!(!vfd.poll(u32::MAX - 10).unwrap());
+        assert!(!vfd.poll(50).unwrap());
+        assert!(vfd.poll(90).unwrap());
+        assert_eq!(vfd.recoveries(), 6);
+        assert!(model.frame().iter().all(|byte| *byte == 0));
+        assert_eq!(model.brightness(), 0x80);
+
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            [0, 1, 2, 7, 40].map(|attempt| policy.backoff(attempt)),
+            [10, 20, 40, 1000, 1000]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Several panels combined into one canvas
+//!
+//! [TiledDisplay] places `N` panels at their own origins, each with its own rotation, and
+//! draws into whichever panel covers a pixel. Only the buffer rows changed since the last
+//! flush are sent to the panels.
+
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_hal::{delay::DelayNs, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::gp1287bi::{Display256x50, VFD256x50, HEIGHT, ROW_BYTES, WIDTH};
+use crate::graphics::{clip_physical, rotated_size, Display, DisplayRotation};
+use crate::traits::EEIDisplay;
+
+/// Placement of one panel on the canvas of a [TiledDisplay]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tile {
+    /// Canvas position of the top left corner of the panel, as seen in `rotation`
+    pub origin: Point,
+    /// Rotation of the panel
+    pub rotation: DisplayRotation,
+}
+
+/// `N` GP1287BI panels drawn as one [DrawTarget]
+pub struct TiledDisplay<const N: usize> {
+    panels: [Display256x50; N],
+    origins: [Point; N],
+    /// buffer rows changed since the last flush, per panel
+    dirty: [Option<(usize, usize)>; N],
+}
+
+impl<const N: usize> TiledDisplay<N> {
+    /// Panels side by side from left to right, all with `rotation`
+    ///
+    /// With [DisplayRotation::Rotate90] three panels make a 768x56 canvas.
+    pub fn new(rotation: DisplayRotation) -> Self {
+        let mut panel = Display256x50::default();
+        panel.set_rotation(rotation);
+        let width = rotated_size(&panel).width as i32;
+        Self::with_tiles(core::array::from_fn(|i| Tile {
+            origin: Point::new(i as i32 * width, 0),
+            rotation,
+        }))
+    }
+
+    /// Panels placed freely, overlapping tiles show the same pixels
+    pub fn with_tiles(tiles: [Tile; N]) -> Self {
+        TiledDisplay {
+            panels: core::array::from_fn(|i| {
+                let mut panel = Display256x50::default();
+                panel.set_rotation(tiles[i].rotation);
+                panel
+            }),
+            origins: tiles.map(|tile| tile.origin),
+            dirty: [None; N],
+        }
+    }
+
+    /// Returns the buffer of panel `index`
+    pub fn panel(&self, index: usize) -> &Display256x50 {
+        &self.panels[index]
+    }
+
+    /// Returns the color of the pixel at `point`, or None if no panel covers it
+    pub fn get_pixel(&self, point: Point) -> Option<Color> {
+        self.panels
+            .iter()
+            .zip(self.origins)
+            .find_map(|(panel, origin)| panel.get_pixel(point - origin))
+    }
+
+    /// Marks every panel as changed, e.g. after one of them was reinitialized
+    pub fn invalidate(&mut self) {
+        self.dirty = [Some((0, HEIGHT as usize)); N];
+    }
+
+    /// Sends the changes of panel `index` to `vfd`
+    ///
+    /// A whole frame is sent with [EEIDisplay::update_frame], otherwise only the changed
+    /// buffer rows. The panels may be on different buses, so each is flushed on its own.
+    pub fn flush_panel<SPI, RST, DELAY>(
+        &mut self,
+        index: usize,
+        vfd: &mut VFD256x50<SPI, RST, DELAY>,
+    ) -> Result<(), SPI::Error>
+    where
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+    {
+        let Some((first, end)) = self.dirty[index] else {
+            return Ok(());
+        };
+        let buffer = self.panels[index].buffer();
+        if first == 0 && end == HEIGHT as usize {
+            vfd.update_frame(buffer)?;
+        } else {
+            let rows = &buffer[first * ROW_BYTES..end * ROW_BYTES];
+            vfd.update_partial_frame(rows, 0, first as u32, WIDTH, (end - first) as u32)?;
+        }
+        self.dirty[index] = None;
+        Ok(())
+    }
+
+    /// Sends the changes of all panels, `vfds` in the order of the tiles
+    pub fn flush<SPI, RST, DELAY>(
+        &mut self,
+        vfds: &mut [VFD256x50<SPI, RST, DELAY>; N],
+    ) -> Result<(), SPI::Error>
+    where
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+    {
+        for (index, vfd) in vfds.iter_mut().enumerate() {
+            self.flush_panel(index, vfd)?;
+        }
+        Ok(())
+    }
+
+    // marks the buffer rows of `area`, in rotated coordinates of panel `index`, as changed
+    fn touch(&mut self, index: usize, area: &Rectangle) {
+        let size = Size::new(WIDTH, HEIGHT);
+        let Some(physical) = clip_physical(area, size, self.panels[index].rotation()) else {
+            return;
+        };
+        let first = physical.top_left.y as usize;
+        let end = first + physical.size.height as usize;
+        self.dirty[index] = Some(match self.dirty[index] {
+            Some((a, b)) => (a.min(first), b.max(end)),
+            None => (first, end),
+        });
+    }
+}
+
+impl<const N: usize> DrawTarget for TiledDisplay<N> {
+    type Color = Color;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            for index in 0..N {
+                let local = point - self.origins[index];
+                if self.panels[index].get_pixel(local).is_some() {
+                    self.panels[index].draw_iter([Pixel(local, color)])?;
+                    self.touch(index, &Rectangle::new(local, Size::new(1, 1)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        for index in 0..N {
+            let origin = self.origins[index];
+            let local = Rectangle::new(area.top_left - origin, area.size);
+            self.panels[index].fill_solid(&local, color)?;
+            self.touch(index, &local);
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> OriginDimensions for TiledDisplay<N> {
+    fn size(&self) -> Size {
+        let corner = self
+            .panels
+            .iter()
+            .zip(self.origins)
+            .map(|(panel, origin)| origin + rotated_size(panel))
+            .fold(Point::zero(), |a, b| a.component_max(b));
+        Size::new(corner.x as u32, corner.y as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::VarDisplay;
+    use crate::model::{Gp1287Model, NoopDelay, NoopPin};
+    use embedded_graphics::mono_font::{ascii::FONT_10X20, MonoTextStyle};
+    use embedded_graphics::primitives::{Line, Primitive, PrimitiveStyle};
+    use embedded_graphics::text::Text;
+    use embedded_graphics::Drawable;
+
+    fn sign<D: DrawTarget<Color = Color>>(display: &mut D) -> Result<(), D::Error> {
+        let style = MonoTextStyle::new(&FONT_10X20, Color::Green);
+        Text::new("across the seam", Point::new(180, 30), style).draw(display)?;
+        Line::new(Point::new(0, 50), Point::new(511, 3))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Green, 3))
+            .draw(display)?;
+        Ok(())
+    }
+
+    #[test]
+    fn crosses_seams() {
+        let mut tiled = TiledDisplay::<2>::new(DisplayRotation::Rotate90);
+        assert_eq!(tiled.size(), Size::new(512, 56));
+        sign(&mut tiled).unwrap();
+
+        let mut buffer = [0u8; 64 * 56];
+        let mut reference = VarDisplay::new(512, 56, &mut buffer);
+        sign(&mut reference).unwrap();
+        for point in tiled.bounding_box().points() {
+            assert_eq!(
+                tiled.get_pixel(point),
+                reference.get_pixel(point),
+                "{:?}",
+                point
+            );
+        }
+        assert_eq!(tiled.get_pixel(Point::new(512, 0)), None);
+    }
+
+    #[test]
+    fn flushes_changed_rows() {
+        let mut tiled = TiledDisplay::<2>::new(DisplayRotation::Rotate90);
+        let (mut left, mut right) = (Gp1287Model::new(), Gp1287Model::new());
+        let mut vfds = [
+            VFD256x50::new(&mut left, NoopPin, NoopDelay).unwrap(),
+            VFD256x50::new(&mut right, NoopPin, NoopDelay).unwrap(),
+        ];
+        sign(&mut tiled).unwrap();
+        tiled.flush(&mut vfds).unwrap();
+        assert_eq!(tiled.dirty, [None; 2]);
+
+        // only four rows of the right panel changed
+        let bar = Rectangle::new(Point::new(300, 0), Size::new(4, 56));
+        tiled.fill_solid(&bar, Color::Green).unwrap();
+        assert_eq!(tiled.dirty[0], None);
+        let (first, end) = tiled.dirty[1].unwrap();
+        assert_eq!(end - first, 4);
+        tiled.flush(&mut vfds).unwrap();
+        assert!(left.frame()[..] == *tiled.panel(0).buffer());
+        assert!(right.frame()[..] == *tiled.panel(1).buffer());
+    }
+}
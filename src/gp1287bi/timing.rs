@@ -0,0 +1,163 @@
+//! Oscillator, scan speed and frame sync of the GP1287
+//!
+//! The multiplexed grids of a VFD are lit one after the other. Filmed with a camera, the scan
+//! can beat against the shutter and show up as rolling bars, which changing the scan speed
+//! or the clock source moves out of the way.
+//!
+//! A frame written while the scan is running shows the old image on some grids and the new one
+//! on the others for one frame, see [PresentMode] for how to avoid that.
+
+/// Clock source of the controller
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Oscillator {
+    /// Internal oscillator, used if no oscillation setting is ever sent
+    #[default]
+    Internal,
+    /// Clock fed into the OSC pin of the module
+    External,
+}
+
+impl Oscillator {
+    /// Argument of the oscillation setting, bit 3 selects the internal oscillator
+    ///
+    /// Unverified: there is no datasheet reference for the bit, check it on the module.
+    pub(crate) fn arg(self) -> u8 {
+        match self {
+            Oscillator::Internal => 0x08,
+            Oscillator::External => 0x00,
+        }
+    }
+}
+
+/// Scan speed of the grids, sent with the internal speed setting
+///
+/// The frame rate is roughly proportional to `1 / (divider * (period + 1))`.
+///
+/// Unverified: only [ScanSpeed::DEFAULT] comes from the initialization sequence, the other
+/// presets and the limits of [ScanSpeed::custom] are guesses without a datasheet reference.
+/// Check them on the module, other values may flicker or wear out the filaments faster.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanSpeed {
+    /// Clock divider of the scan
+    divider: u8,
+    /// Clocks per grid minus one
+    period: u8,
+}
+
+impl ScanSpeed {
+    /// Speed set by the initialization sequence
+    pub const DEFAULT: ScanSpeed = ScanSpeed {
+        divider: 0x20,
+        period: 0x3F,
+    };
+    /// About 25% slower than the default
+    pub const SLOW: ScanSpeed = ScanSpeed {
+        divider: 0x28,
+        period: 0x3F,
+    };
+    /// About 33% faster than the default, reduces flicker when filmed
+    pub const FAST: ScanSpeed = ScanSpeed {
+        divider: 0x18,
+        period: 0x3F,
+    };
+
+    /// A scan speed other than the presets
+    ///
+    /// `divider` is the clock divider of the scan and `period` the clocks per grid minus one.
+    /// Returns None unless the frame rate lies between [ScanSpeed::SLOW] and [ScanSpeed::FAST],
+    /// the range spanned by the presets.
+    pub fn custom(divider: u8, period: u8) -> Option<ScanSpeed> {
+        let clocks = |speed: ScanSpeed| speed.divider as u32 * (speed.period as u32 + 1);
+        let speed = ScanSpeed { divider, period };
+        (clocks(ScanSpeed::FAST)..=clocks(ScanSpeed::SLOW))
+            .contains(&clocks(speed))
+            .then_some(speed)
+    }
+
+    /// Clock divider of the scan
+    pub fn divider(self) -> u8 {
+        self.divider
+    }
+
+    /// Clocks per grid minus one
+    pub fn period(self) -> u8 {
+        self.period
+    }
+
+    /// Arguments of the internal speed setting
+    pub(crate) fn args(self) -> [u8; 4] {
+        [self.divider, self.period, 0x00, 0x01]
+    }
+}
+
+impl Default for ScanSpeed {
+    fn default() -> Self {
+        ScanSpeed::DEFAULT
+    }
+}
+
+/// How [VFD256x50::present](super::VFD256x50::present) puts a frame into the GRAM
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Write right away, the frame may tear
+    #[default]
+    Immediate,
+    /// Avoid tearing as configured with [VFD256x50::set_frame_sync](super::VFD256x50::set_frame_sync)
+    Synced,
+}
+
+/// How synced presentation is done
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameSync {
+    /// The controller holds back GRAM writes until the next frame starts
+    #[default]
+    Controller,
+    /// Fallback for modules without frame sync: the frame is written in bands paced by
+    /// the frame period, so each band lands within a single scan
+    Timed(FrameTiming),
+}
+
+/// Timing model for [FrameSync::Timed]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// Duration of one frame in microseconds
+    pub frame_us: u32,
+    /// Buffer rows written per frame, small enough to be sent well within one frame
+    pub rows_per_frame: u16,
+}
+
+impl Default for FrameTiming {
+    /// About 120 Hz with [ScanSpeed::DEFAULT] and a third of the frame per band
+    ///
+    /// Unverified: the frame rate is an estimate, measure it on the module.
+    fn default() -> Self {
+        FrameTiming {
+            frame_us: 8_333,
+            rows_per_frame: 86,
+        }
+    }
+}
+
+/// Arguments of the frame sync setting
+///
+/// Unverified: the init sequence sends `0x00`, there is no datasheet reference for `0x01`
+/// as "on", check it on the module. [FrameSync::Timed] works without it.
+pub(crate) const FRAME_SYNC_OFF: u8 = 0x00;
+pub(crate) const FRAME_SYNC_ON: u8 = 0x01;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_scan_speed() {
+        assert_eq!(ScanSpeed::custom(0x20, 0x3F), Some(ScanSpeed::DEFAULT));
+        assert_eq!(
+            ScanSpeed::custom(0x30, 0x2A).map(ScanSpeed::args),
+            Some([0x30, 0x2A, 0, 1])
+        );
+        assert_eq!(ScanSpeed::custom(0x28, 0x40), None);
+        assert_eq!(ScanSpeed::custom(0x17, 0x3F), None);
+        assert_eq!(ScanSpeed::custom(0, 0xFF), None);
+    }
+}
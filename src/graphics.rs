@@ -1,11 +1,18 @@
 //! Graphics Support for EPDs
 
-use crate::buffer_len;
 use crate::color::Color;
+use core::marker::PhantomData;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+mod golden;
+mod layout;
+
+pub use self::golden::{compare, Golden, Mismatch};
+pub use self::layout::{ColumnMajor, PixelLayout, RowMajor, VerticalPages};
 
 /// Displayrotation
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum DisplayRotation {
     /// No rotation
     #[default]
@@ -18,6 +25,40 @@ pub enum DisplayRotation {
     Rotate270,
 }
 
+/// How a frame is cut into strips for banded rendering
+///
+/// The direction refers to the unrotated buffer, where rows are
+/// [WIDTH](crate::gp1287bi::WIDTH) pixels wide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StripDirection {
+    /// Strips of whole buffer rows
+    #[default]
+    Horizontal,
+    /// Strips of whole bytes of every buffer row, eight pixels wide each
+    Vertical,
+}
+
+/// Errors while rendering and sending a frame strip by strip
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BandError<E, D> {
+    /// The scratch buffer can't hold a single strip
+    Buffer(VarDisplayError),
+    /// Drawing a strip failed
+    Draw(D),
+    /// Writing to the display failed
+    Spi(E),
+}
+
+impl<E: core::fmt::Display, D: core::fmt::Display> core::fmt::Display for BandError<E, D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BandError::Buffer(e) => write!(f, "No strip fits: {}", e),
+            BandError::Draw(e) => write!(f, "Drawing failed: {}", e),
+            BandError::Spi(e) => write!(f, "Writing failed: {}", e),
+        }
+    }
+}
+
 /// Display specific pixel output configuration
 ///
 /// Different chromatic displays differently treat the bits in chromatic color planes.
@@ -61,6 +102,13 @@ pub trait Display: DrawTarget<Color = Color> {
     /// Get the current rotation of the display
     fn rotation(&self) -> DisplayRotation;
 
+    /// Returns the color of the pixel at `point`, or None if it is outside of the display
+    ///
+    /// The point is in the same rotated coordinates used for drawing.
+    fn get_pixel(&self, point: Point) -> Option<Color> {
+        layout_pixel::<RowMajor, Self>(self, point)
+    }
+
     /// Helperfunction for the Embedded Graphics draw trait
     ///
     /// Becomes uneccesary when const_generics become stablised
@@ -70,30 +118,95 @@ pub trait Display: DrawTarget<Color = Color> {
         height: u32,
         pixel: Pixel<Color>,
     ) -> Result<(), Self::Error> {
-        let rotation = self.rotation();
-        let buffer = self.get_mut_buffer();
+        layout_draw::<RowMajor, Self>(self, width, height, pixel)
+    }
+}
+
+/// A [Display] whose buffer is packed with a known [PixelLayout]
+///
+/// Kept apart from [Display], so displays implemented outside of this crate keep working
+/// unchanged. Their buffers are packed row major like [RowMajor].
+pub trait LayoutDisplay: Display {
+    /// How the pixels are packed into the buffer
+    type Layout: PixelLayout;
+}
+
+// [Display::get_pixel] for a buffer packed with `L`
+pub(crate) fn layout_pixel<L: PixelLayout, D: Display + ?Sized>(
+    display: &D,
+    point: Point,
+) -> Option<Color> {
+    let Size { width, height } = display.bounding_box().size;
+    let rotation = display.rotation();
+    if outside_display(point, width, height, rotation) {
+        return None;
+    }
+    let (index, bit) = find_position::<L>(point.x as u32, point.y as u32, width, height, rotation);
+    match display.buffer()[index] & bit {
+        0 => Some(Color::Dark),
+        _ => Some(Color::Green),
+    }
+}
+
+// [Display::draw_helper] for a buffer packed with `L`
+pub(crate) fn layout_draw<L: PixelLayout, D: Display + ?Sized>(
+    display: &mut D,
+    width: u32,
+    height: u32,
+    pixel: Pixel<Color>,
+) -> Result<(), D::Error> {
+    let rotation = display.rotation();
+    let buffer = display.get_mut_buffer();
 
-        let Pixel(point, color) = pixel;
-        if outside_display(point, width, height, rotation) {
-            return Ok(());
+    let Pixel(point, color) = pixel;
+    if outside_display(point, width, height, rotation) {
+        return Ok(());
+    }
+
+    // Give us index inside the buffer and the bit-position in that u8 which needs to be changed
+    let (index, bit) = find_position::<L>(point.x as u32, point.y as u32, width, height, rotation);
+
+    // "Draw" the Pixel on that bit
+    match color {
+        // Black
+        Color::Dark => {
+            buffer[index] &= !bit;
         }
+        // White
+        Color::Green => {
+            buffer[index] |= bit;
+        }
+    }
+    Ok(())
+}
 
-        // Give us index inside the buffer and the bit-position in that u8 which needs to be changed
-        let (index, bit) = find_position(point.x as u32, point.y as u32, width, height, rotation);
-        let index = index as usize;
+/// Errors when creating a [VarDisplay]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum VarDisplayError {
+    /// The buffer is too small for the requested size
+    BufferTooSmall {
+        /// Bytes needed
+        required: usize,
+        /// Bytes available
+        actual: usize,
+    },
+    /// A row of the window doesn't fit into the stride of the buffer
+    StrideTooSmall,
+    /// The window is not completely inside of the parent display
+    OutOfBounds,
+}
 
-        // "Draw" the Pixel on that bit
-        match color {
-            // Black
-            Color::Dark => {
-                buffer[index] &= !bit;
-            }
-            // White
-            Color::Green => {
-                buffer[index] |= bit;
-            }
+impl core::fmt::Display for VarDisplayError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            VarDisplayError::BufferTooSmall { required, actual } => write!(
+                f,
+                "Buffer too small: {} bytes required, {} available",
+                required, actual
+            ),
+            VarDisplayError::StrideTooSmall => write!(f, "Window wider than the buffer stride"),
+            VarDisplayError::OutOfBounds => write!(f, "Window outside of the display"),
         }
-        Ok(())
     }
 }
 
@@ -102,30 +215,168 @@ pub trait Display: DrawTarget<Color = Color> {
 /// The buffer can be created as following:
 /// buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 8 * HEIGHT]
 /// If WIDTH is not a multiple of 8, don't forget to round it up (ie. (WIDTH + 7) / 8)
-pub struct VarDisplay<'a> {
+///
+/// It can also be a window into a larger buffer, see [VarDisplay::window],
+/// or store only a strip of a larger display, see [VarDisplay::strip].
+/// The buffer is packed like the GP1287 GRAM unless another [PixelLayout] is chosen,
+/// see [VarDisplay::with_layout].
+pub struct VarDisplay<'a, L = RowMajor> {
     width: u32,
     height: u32,
     rotation: DisplayRotation,
     buffer: &'a mut [u8], //buffer: Box<u8>//[u8; 15000]
+    /// bytes per row of the buffer
+    stride: usize,
+    /// position of the window inside the buffer, in pixels
+    origin: Point,
+    /// unrotated pixels stored in the buffer, everything else is dropped
+    clip: Rectangle,
+    layout: PhantomData<L>,
 }
 
 impl<'a> VarDisplay<'a> {
     /// Create a new variable sized display.
     ///
     /// Buffersize must be at least (width + 7) / 8 * height bytes.
+    ///
+    /// # Panics
+    ///
+    /// If the buffer is too small, use [VarDisplay::try_new] to handle that case.
     pub fn new(width: u32, height: u32, buffer: &'a mut [u8]) -> VarDisplay<'a> {
-        let len = buffer.len() as u32;
-        assert!(buffer_len(width as usize, height as usize) >= len as usize);
-        VarDisplay {
-            width,
-            height,
+        match Self::try_new(width, height, buffer) {
+            Ok(display) => display,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Create a new variable sized display, failing if the buffer is too small
+    pub fn try_new(
+        width: u32,
+        height: u32,
+        buffer: &'a mut [u8],
+    ) -> Result<VarDisplay<'a>, VarDisplayError> {
+        Self::with_layout(width, height, buffer)
+    }
+
+    /// Creates a display drawing only into a part of a larger buffer
+    ///
+    /// `stride` is the number of bytes per row of the buffer and `origin` the top left corner
+    /// of the window in unrotated pixels of the buffer. Drawing is clipped to the window,
+    /// but [Display::buffer] still returns the whole buffer.
+    pub fn window(
+        buffer: &'a mut [u8],
+        stride: usize,
+        origin: Point,
+        size: Size,
+    ) -> Result<VarDisplay<'a>, VarDisplayError> {
+        Self::window_with_layout(buffer, stride, origin, size)
+    }
+
+    /// Creates a display of `width` x `height` pixels which only stores the pixels inside `clip`
+    ///
+    /// `clip` is given in unrotated pixels and packed into `buffer` as a display of its own,
+    /// so a frame can be rendered strip by strip with a small buffer. Pixels outside of the clip
+    /// are dropped when drawing and [Display::get_pixel] returns None for them.
+    pub fn strip(
+        width: u32,
+        height: u32,
+        clip: Rectangle,
+        buffer: &'a mut [u8],
+    ) -> Result<VarDisplay<'a>, VarDisplayError> {
+        let full = Rectangle::new(Point::zero(), Size::new(width, height));
+        if clip.is_zero_sized() || full.intersection(&clip) != clip {
+            return Err(VarDisplayError::OutOfBounds);
+        }
+        let stride = RowMajor::stride(clip.size.width, clip.size.height);
+        let mut display = Self::window_with_layout(buffer, stride, Point::zero(), clip.size)?;
+        display.width = width;
+        display.height = height;
+        display.origin = Point::zero() - clip.top_left;
+        display.clip = clip;
+        Ok(display)
+    }
+}
+
+impl<'a, L: PixelLayout> VarDisplay<'a, L> {
+    /// Like [VarDisplay::try_new], with the buffer packed according to `L`
+    pub fn with_layout(
+        width: u32,
+        height: u32,
+        buffer: &'a mut [u8],
+    ) -> Result<Self, VarDisplayError> {
+        let stride = L::stride(width, height);
+        Self::window_with_layout(buffer, stride, Point::zero(), Size::new(width, height))
+    }
+
+    /// Like [VarDisplay::window], with the buffer packed according to `L`
+    ///
+    /// The meaning of `stride` depends on the layout, see [PixelLayout].
+    pub fn window_with_layout(
+        buffer: &'a mut [u8],
+        stride: usize,
+        origin: Point,
+        size: Size,
+    ) -> Result<Self, VarDisplayError> {
+        if origin.x < 0 || origin.y < 0 {
+            return Err(VarDisplayError::OutOfBounds);
+        }
+        let (right, bottom) = (origin.x as u32 + size.width, origin.y as u32 + size.height);
+        if !L::fits(right, bottom, stride) {
+            return Err(VarDisplayError::StrideTooSmall);
+        }
+        let required = L::buffer_len(right, bottom, stride);
+        if buffer.len() < required {
+            return Err(VarDisplayError::BufferTooSmall {
+                required,
+                actual: buffer.len(),
+            });
+        }
+        Ok(VarDisplay {
+            width: size.width,
+            height: size.height,
             rotation: DisplayRotation::default(),
             buffer,
+            stride,
+            origin,
+            clip: Rectangle::new(Point::zero(), size),
+            layout: PhantomData,
+        })
+    }
+
+    // index in the buffer and bit mask of a pixel, None if it is outside of the window or clip
+    fn position(&self, point: Point) -> Option<(usize, u8)> {
+        if outside_display(point, self.width, self.height, self.rotation) {
+            return None;
+        }
+        let (nx, ny) = find_rotation(
+            point.x as u32,
+            point.y as u32,
+            self.width,
+            self.height,
+            self.rotation,
+        );
+        let p = Point::new(nx as i32, ny as i32);
+        if !self.clip.contains(p) {
+            return None;
         }
+        let p = p + self.origin;
+        Some(L::position(p.x as u32, p.y as u32, self.stride))
+    }
+
+    fn is_window(&self) -> bool {
+        self.origin != Point::zero()
+            || self.stride != L::stride(self.width, self.height)
+            || self.clip.size != Size::new(self.width, self.height)
+    }
+
+    // unrotated pixels of `area` inside of the clip, moved to their position in the buffer
+    fn physical_window(&self, area: &Rectangle) -> Rectangle {
+        let area = area.intersection(&self.clip);
+        Rectangle::new(area.top_left + self.origin, area.size)
     }
 }
 
-impl<'a> DrawTarget for VarDisplay<'a> {
+impl<'a, L: PixelLayout> DrawTarget for VarDisplay<'a, L> {
     type Color = Color;
     type Error = core::convert::Infallible;
 
@@ -133,20 +384,49 @@ impl<'a> DrawTarget for VarDisplay<'a> {
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
-        for pixel in pixels {
-            self.draw_helper(self.width, self.height, pixel)?;
+        for Pixel(point, color) in pixels {
+            if let Some((index, bit)) = self.position(point) {
+                match color {
+                    Color::Dark => self.buffer[index] &= !bit,
+                    Color::Green => self.buffer[index] |= bit,
+                }
+            }
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let size = Size::new(self.width, self.height);
+        if let Some(area) = clip_physical(area, size, self.rotation) {
+            let area = self.physical_window(&area);
+            L::fill(self.buffer, self.stride, &area, color);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, L: PixelLayout> LayoutDisplay for VarDisplay<'a, L> {
+    type Layout = L;
 }
 
-impl<'a> OriginDimensions for VarDisplay<'a> {
+impl<'a, L> OriginDimensions for VarDisplay<'a, L> {
     fn size(&self) -> Size {
         Size::new(self.width, self.height)
     }
 }
 
-impl<'a> Display for VarDisplay<'a> {
+impl<'a, L: PixelLayout> Display for VarDisplay<'a, L> {
+    fn clear_buffer(&mut self, background_color: Color) {
+        if !self.is_window() {
+            let len = L::buffer_len(self.width, self.height, self.stride);
+            self.buffer[..len].fill(background_color.get_byte_value());
+            return;
+        }
+        // only touch the pixels inside of the window
+        let window = self.physical_window(&self.clip);
+        L::fill(self.buffer, self.stride, &window, background_color);
+    }
+
     fn buffer(&self) -> &[u8] {
         self.buffer
     }
@@ -162,6 +442,104 @@ impl<'a> Display for VarDisplay<'a> {
     fn rotation(&self) -> DisplayRotation {
         self.rotation
     }
+
+    /// Draws through the window and clip, `width` and `height` are those of the display
+    fn draw_helper(
+        &mut self,
+        _width: u32,
+        _height: u32,
+        pixel: Pixel<Color>,
+    ) -> Result<(), Self::Error> {
+        self.draw_iter([pixel])
+    }
+
+    fn get_pixel(&self, point: Point) -> Option<Color> {
+        let (index, bit) = self.position(point)?;
+        match self.buffer[index] & bit {
+            0 => Some(Color::Dark),
+            _ => Some(Color::Green),
+        }
+    }
+}
+
+/// Maps a rectangle in rotated coordinates to the unrotated pixels of the buffer
+///
+/// Returns None if the rectangle is not completely inside of the display.
+pub(crate) fn physical_area(
+    area: &Rectangle,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> Option<Rectangle> {
+    let bottom_right = area.bottom_right()?;
+    if outside_display(area.top_left, width, height, rotation)
+        || outside_display(bottom_right, width, height, rotation)
+    {
+        return None;
+    }
+    let corner = |p: Point| {
+        let (x, y) = find_rotation(p.x as u32, p.y as u32, width, height, rotation);
+        Point::new(x as i32, y as i32)
+    };
+    Some(Rectangle::with_corners(
+        corner(area.top_left),
+        corner(bottom_right),
+    ))
+}
+
+/// Clips a rectangle in rotated coordinates to the display and maps it to the unrotated buffer
+///
+/// `size` is the unrotated size of the display. Returns None if nothing is left.
+pub(crate) fn clip_physical(
+    area: &Rectangle,
+    size: Size,
+    rotation: DisplayRotation,
+) -> Option<Rectangle> {
+    let bounds = match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => size,
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+            Size::new(size.height, size.width)
+        }
+    };
+    let area = area.intersection(&Rectangle::new(Point::zero(), bounds));
+    if area.is_zero_sized() {
+        return None;
+    }
+    physical_area(&area, size.width, size.height, rotation)
+}
+
+impl<'a, L: PixelLayout> core::fmt::Debug for VarDisplay<'a, L> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        ascii_art(self, f)
+    }
+}
+
+/// Writes the display as ASCII art, `#` for lit and `.` for dark pixels
+pub(crate) fn ascii_art<D: Display>(
+    display: &D,
+    f: &mut core::fmt::Formatter<'_>,
+) -> core::fmt::Result {
+    let size = rotated_size(display);
+    writeln!(f, "{}x{} {:?}", size.width, size.height, display.rotation())?;
+    for y in 0..size.height as i32 {
+        for x in 0..size.width as i32 {
+            let lit = display.get_pixel(Point::new(x, y)) == Some(Color::Green);
+            f.write_str(if lit { "#" } else { "." })?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// Size of the display as seen through its current rotation
+pub(crate) fn rotated_size<D: Display>(display: &D) -> Size {
+    let size = display.bounding_box().size;
+    match display.rotation() {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => size,
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+            Size::new(size.height, size.width)
+        }
+    }
 }
 
 // Checks if a pos is outside the defined display
@@ -209,12 +587,274 @@ fn find_rotation(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotat
     (nx, ny)
 }
 
-#[rustfmt::skip]
 //returns index position in the u8-slice and the bit-position inside that u8
-fn find_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
+fn find_position<L: PixelLayout>(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> (usize, u8) {
     let (nx, ny) = find_rotation(x, y, width, height, rotation);
-    (
-        nx / 8 + ((width + 7) / 8) * ny,
-        0x80 >> (nx % 8),
-    )
+    L::position(nx, ny, L::stride(width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_len;
+    use embedded_graphics::primitives::{Circle, Line, Primitive, PrimitiveStyle, Rectangle};
+    use proptest::prelude::*;
+    use std::vec;
+    use std::vec::Vec;
+
+    const ROTATIONS: [DisplayRotation; 4] = [
+        DisplayRotation::Rotate0,
+        DisplayRotation::Rotate90,
+        DisplayRotation::Rotate180,
+        DisplayRotation::Rotate270,
+    ];
+
+    fn rotation() -> impl Strategy<Value = DisplayRotation> {
+        (0..4usize).prop_map(|i| ROTATIONS[i])
+    }
+
+    fn color() -> impl Strategy<Value = Color> {
+        any::<bool>().prop_map(|lit| if lit { Color::Green } else { Color::Dark })
+    }
+
+    // bits after the last pixel of every row which must never be touched
+    fn padding(buffer: &[u8], width: u32) -> Vec<u8> {
+        let mask = match width % 8 {
+            0 => 0,
+            used => 0xff >> used,
+        };
+        buffer
+            .chunks(width.div_ceil(8) as usize)
+            .map(|row| row[row.len() - 1] & mask)
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn pixels_outside_never_change_buffer(
+            width in 1u32..80,
+            height in 1u32..80,
+            rotation in rotation(),
+            pixels in prop::collection::vec((-100i32..180, -100i32..180, color()), 0..64),
+        ) {
+            let mut buffer = vec![0x5au8; buffer_len(width as usize, height as usize)];
+            let mut display = VarDisplay::new(width, height, &mut buffer);
+            display.set_rotation(rotation);
+            let size = rotated_size(&display);
+            let padding_before = padding(display.buffer(), width);
+
+            for (x, y, color) in pixels {
+                let point = Point::new(x, y);
+                let before = display.buffer().to_vec();
+                Pixel(point, color).draw(&mut display).unwrap();
+                let inside = x >= 0 && y >= 0 && (x as u32) < size.width && (y as u32) < size.height;
+                if inside {
+                    prop_assert_eq!(display.get_pixel(point), Some(color));
+                } else {
+                    prop_assert_eq!(display.buffer(), &before[..]);
+                    prop_assert_eq!(display.get_pixel(point), None);
+                }
+            }
+            prop_assert_eq!(padding(display.buffer(), width), padding_before);
+        }
+
+        #[test]
+        fn primitives_stay_inside(
+            width in 1u32..80,
+            height in 1u32..80,
+            rotation in rotation(),
+            color in color(),
+            (x0, y0, x1, y1) in (-60i32..140, -60i32..140, -60i32..140, -60i32..140),
+            diameter in 0u32..100,
+        ) {
+            let mut buffer = vec![0xa5u8; buffer_len(width as usize, height as usize)];
+            let mut display = VarDisplay::new(width, height, &mut buffer);
+            display.set_rotation(rotation);
+            let padding_before = padding(display.buffer(), width);
+
+            let stroke = PrimitiveStyle::with_stroke(color, 3);
+            let fill = PrimitiveStyle::with_fill(color);
+            Line::new(Point::new(x0, y0), Point::new(x1, y1)).into_styled(stroke).draw(&mut display).unwrap();
+            Rectangle::with_corners(Point::new(x0, y0), Point::new(x1, y1)).into_styled(fill).draw(&mut display).unwrap();
+            Circle::new(Point::new(x1, y0), diameter).into_styled(stroke).draw(&mut display).unwrap();
+            display.clear(color).unwrap();
+
+            prop_assert_eq!(padding(display.buffer(), width), padding_before);
+        }
+
+        #[test]
+        fn four_rotations_are_identity(
+            (width, height, x, y) in (1u32..300, 1u32..300)
+                .prop_flat_map(|(w, h)| (Just(w), Just(h), 0..w, 0..h))
+        ) {
+            // each step maps a point of a width x height image into the height x width image rotated by 90 degrees
+            let mut point = (x, y);
+            let (mut w, mut h) = (height, width);
+            for _ in 0..4 {
+                point = find_rotation(point.0, point.1, w, h, DisplayRotation::Rotate90);
+                core::mem::swap(&mut w, &mut h);
+            }
+            prop_assert_eq!(point, (x, y));
+
+            // and the other rotations are compositions of it
+            let once = find_rotation(x, y, height, width, DisplayRotation::Rotate90);
+            let twice = find_rotation(once.0, once.1, width, height, DisplayRotation::Rotate90);
+            prop_assert_eq!(find_rotation(x, y, width, height, DisplayRotation::Rotate180), twice);
+        }
+    }
+
+    #[test]
+    fn buffer_size_is_checked() {
+        let mut buffer = [0u8; 6];
+        assert!(VarDisplay::try_new(10, 3, &mut buffer).is_ok());
+        assert_eq!(
+            VarDisplay::try_new(10, 4, &mut buffer).err(),
+            Some(VarDisplayError::BufferTooSmall {
+                required: 8,
+                actual: 6
+            })
+        );
+        assert_eq!(
+            VarDisplay::window(&mut buffer, 2, Point::new(12, 0), Size::new(5, 1)).err(),
+            Some(VarDisplayError::StrideTooSmall)
+        );
+    }
+
+    #[test]
+    fn window_confines_drawing() {
+        let mut buffer = [0u8; 3 * 4];
+        let mut window =
+            VarDisplay::window(&mut buffer, 3, Point::new(5, 1), Size::new(10, 2)).unwrap();
+        Rectangle::new(Point::new(-5, -5), Size::new(50, 50))
+            .into_styled(PrimitiveStyle::with_fill(Color::Green))
+            .draw(&mut window)
+            .unwrap();
+        Pixel(Point::new(9, 1), Color::Dark)
+            .draw(&mut window)
+            .unwrap();
+        window.clear_buffer(Color::Green);
+        Pixel(Point::new(0, 0), Color::Dark)
+            .draw(&mut window)
+            .unwrap();
+        // the helper is confined like drawing
+        window
+            .draw_helper(24, 4, Pixel(Point::new(1, 0), Color::Dark))
+            .unwrap();
+        window
+            .draw_helper(24, 4, Pixel(Point::new(12, 0), Color::Green))
+            .unwrap();
+
+        let mut parent = VarDisplay::new(24, 4, &mut buffer);
+        crate::assert_display_eq!(
+            parent,
+            "
+            ........................
+            .......########.........
+            .....##########.........
+            ........................
+            "
+        );
+        parent.clear_buffer(Color::Dark);
+        assert_eq!(parent.buffer(), &[0u8; 12]);
+    }
+
+    #[test]
+    fn rotated_window_matches_parent() {
+        use crate::gp1287bi::Display256x50;
+
+        for rotation in ROTATIONS {
+            let area = Rectangle::new(Point::new(3, 5), Size::new(20, 9));
+            let mut expected = Display256x50::default();
+            expected.set_rotation(rotation);
+            Pixel(Point::new(3 + 7, 5 + 2), Color::Green)
+                .draw(&mut expected)
+                .unwrap();
+
+            let mut display = Display256x50::default();
+            display.set_rotation(rotation);
+            let mut window = display.window(area).unwrap();
+            Pixel(Point::new(7, 2), Color::Green)
+                .draw(&mut window)
+                .unwrap();
+            // outside of the window
+            Pixel(Point::new(20, 2), Color::Green)
+                .draw(&mut window)
+                .unwrap();
+            assert_eq!(display.buffer(), expected.buffer());
+        }
+    }
+
+    // fill_solid takes the fast path of the layout, draw_iter sets one pixel after the other
+    fn fast_fill_matches_pixels<L: PixelLayout>() {
+        use crate::gp1287bi::Display256x50;
+
+        let area = Rectangle::new(Point::new(-3, 5), Size::new(30, 21));
+        for rotation in ROTATIONS {
+            let mut fast = Display256x50::<L>::with_layout();
+            let mut slow = Display256x50::<L>::with_layout();
+            fast.set_rotation(rotation);
+            slow.set_rotation(rotation);
+            fast.fill_solid(&area, Color::Green).unwrap();
+            slow.draw_iter(area.points().map(|p| Pixel(p, Color::Green)))
+                .unwrap();
+            assert_eq!(fast.buffer(), slow.buffer());
+
+            let mut buffer = [0u8; 8 * 10];
+            let mut window = VarDisplay::<L>::window_with_layout(
+                &mut buffer,
+                8,
+                Point::new(2, 3),
+                Size::new(5, 4),
+            )
+            .unwrap();
+            window.set_rotation(rotation);
+            let size = rotated_size(&window);
+            let cover = Rectangle::new(Point::new(1, -2), Size::new(30, 30));
+            window.fill_solid(&cover, Color::Green).unwrap();
+            let lit = Rectangle::new(Point::zero(), size)
+                .points()
+                .filter(|p| window.get_pixel(*p) == Some(Color::Green));
+            assert_eq!(lit.count() as u32, (size.width - 1) * size.height);
+            let ones = buffer.iter().map(|b| b.count_ones()).sum::<u32>();
+            assert_eq!(ones, (size.width - 1) * size.height);
+        }
+    }
+
+    #[test]
+    fn layouts_fill_fast() {
+        fast_fill_matches_pixels::<RowMajor>();
+        fast_fill_matches_pixels::<ColumnMajor>();
+        fast_fill_matches_pixels::<VerticalPages>();
+    }
+
+    #[test]
+    fn every_rotation_reaches_every_pixel() {
+        let (width, height) = (13u32, 5u32);
+        for rotation in ROTATIONS {
+            let mut buffer = [0u8; 2 * 5];
+            let mut display = VarDisplay::new(width, height, &mut buffer);
+            display.set_rotation(rotation);
+            let size = rotated_size(&display);
+            for y in 0..size.height as i32 {
+                for x in 0..size.width as i32 {
+                    Pixel(Point::new(x, y), Color::Green)
+                        .draw(&mut display)
+                        .unwrap();
+                }
+            }
+            assert_all_lit(display.buffer(), width);
+        }
+    }
+
+    fn assert_all_lit(buffer: &[u8], width: u32) {
+        for row in buffer.chunks(width.div_ceil(8) as usize) {
+            assert_eq!(row, [0xff, 0xf8]);
+        }
+    }
 }
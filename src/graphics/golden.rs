@@ -0,0 +1,270 @@
+//! Comparing displays with golden images
+//!
+//! Golden images are either plain PBMs (`P1`, a 1 is a lit pixel) or ASCII art,
+//! one line per row with `#` for lit and `.` for dark pixels:
+//!
+//! ```text
+//! .##.
+//! #..#
+//! .##.
+//! ```
+
+use super::{rotated_size, Display};
+use core::fmt;
+use embedded_graphics_core::prelude::*;
+
+/// Asserts that a [Display] matches a golden image
+///
+/// The golden image is a `&str` with either a plain PBM or ASCII art, see [compare](crate::graphics::compare).
+/// On mismatch the expected and the actual image are printed side by side, with `+` marking
+/// pixels which are lit but should be dark and `-` marking pixels which should be lit.
+///
+/// ```
+/// use eei_vfd::assert_display_eq;
+/// use eei_vfd::graphics::VarDisplay;
+/// use eei_vfd::prelude::*;
+/// use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+///
+/// let mut buffer = [0u8; 3];
+/// let mut display = VarDisplay::new(4, 3, &mut buffer);
+/// Line::new(Point::new(1, 1), Point::new(3, 1))
+///     .into_styled(PrimitiveStyle::with_stroke(Color::Green, 1))
+///     .draw(&mut display)
+///     .unwrap();
+///
+/// assert_display_eq!(display, "
+///     ....
+///     .###
+///     ....
+/// ");
+/// ```
+#[macro_export]
+macro_rules! assert_display_eq {
+    ($display:expr, $golden:expr $(,)?) => {
+        if let Err(mismatch) = $crate::graphics::compare(&$display, $golden) {
+            panic!("display does not match the golden image\n{}", mismatch);
+        }
+    };
+}
+
+/// Compares `display` with a golden image, see [assert_display_eq]
+pub fn compare<'a, 'd, D: Display>(
+    display: &'d D,
+    golden: &'a str,
+) -> Result<(), Mismatch<'a, 'd, D>> {
+    let golden = Golden::parse(golden).ok_or(Mismatch::Invalid)?;
+    let size = rotated_size(display);
+    if size != Size::new(golden.width, golden.height) {
+        return Err(Mismatch::Size {
+            expected: Size::new(golden.width, golden.height),
+            actual: size,
+        });
+    }
+    let differs = golden
+        .pixels()
+        .any(|(point, lit)| lit != is_lit(display, point));
+    if differs {
+        return Err(Mismatch::Pixels { golden, display });
+    }
+    Ok(())
+}
+
+/// Why a display does not match its golden image
+pub enum Mismatch<'a, 'd, D> {
+    /// The golden image could not be parsed
+    Invalid,
+    /// The sizes differ
+    Size {
+        /// Size of the golden image
+        expected: Size,
+        /// Size of the display in its current rotation
+        actual: Size,
+    },
+    /// Some pixels differ
+    Pixels {
+        /// The parsed golden image
+        golden: Golden<'a>,
+        /// The display which was compared
+        display: &'d D,
+    },
+}
+
+impl<'a, 'd, D: Display> fmt::Display for Mismatch<'a, 'd, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Invalid => write!(f, "golden image is neither a plain PBM nor ASCII art"),
+            Mismatch::Size { expected, actual } => write!(
+                f,
+                "expected a {}x{} image, display is {}x{}",
+                expected.width, expected.height, actual.width, actual.height
+            ),
+            Mismatch::Pixels { golden, display } => {
+                let width = golden.width as usize;
+                writeln!(f, "{:<width$}   actual", "expected", width = width)?;
+                let mut pixels = golden.pixels();
+                for _ in 0..golden.height {
+                    let row = pixels.clone().take(width);
+                    for (_, lit) in row.clone() {
+                        f.write_str(if lit { "#" } else { "." })?;
+                    }
+                    f.write_str("   ")?;
+                    for (point, lit) in row {
+                        let symbol = match (lit, is_lit(*display, point)) {
+                            (true, true) => "#",
+                            (false, false) => ".",
+                            (false, true) => "+",
+                            (true, false) => "-",
+                        };
+                        f.write_str(symbol)?;
+                    }
+                    writeln!(f)?;
+                    pixels.nth(width - 1);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'a, 'd, D: Display> fmt::Debug for Mismatch<'a, 'd, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+fn is_lit<D: Display>(display: &D, point: Point) -> bool {
+    display.get_pixel(point).map(|c| c.get_bit_value()) == Some(1)
+}
+
+/// A parsed golden image, see [compare]
+#[derive(Clone, Copy)]
+pub struct Golden<'a> {
+    width: u32,
+    height: u32,
+    body: &'a str,
+    pbm: bool,
+}
+
+impl<'a> Golden<'a> {
+    /// Parses a plain PBM or ASCII art
+    pub fn parse(src: &'a str) -> Option<Self> {
+        let trimmed = src.trim_start();
+        let golden = match trimmed.strip_prefix("P1") {
+            Some(rest) => {
+                let mut rest = rest;
+                let mut header = [0u32; 2];
+                for value in header.iter_mut() {
+                    rest = skip_comments(rest);
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    *value = rest[..end].parse().ok()?;
+                    rest = &rest[end..];
+                }
+                Golden {
+                    width: header[0],
+                    height: header[1],
+                    body: rest,
+                    pbm: true,
+                }
+            }
+            None => {
+                let mut rows = src.lines().map(str::trim).filter(|l| !l.is_empty());
+                let width = rows.next()?.len();
+                if rows.clone().any(|row| row.len() != width) {
+                    return None;
+                }
+                Golden {
+                    width: width as u32,
+                    height: rows.count() as u32 + 1,
+                    body: src,
+                    pbm: false,
+                }
+            }
+        };
+        let valid = golden
+            .body
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .all(|c| bit(golden.pbm, c).is_some());
+        let count = golden.pixels().count() as u32;
+        (valid && count == golden.width * golden.height).then_some(golden)
+    }
+
+    /// Width of the image
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// All pixels in row major order, true is lit
+    pub fn pixels(&self) -> impl Iterator<Item = (Point, bool)> + Clone + 'a {
+        let (width, pbm) = (self.width.max(1) as i32, self.pbm);
+        self.body
+            .chars()
+            .filter_map(move |c| bit(pbm, c))
+            .enumerate()
+            .map(move |(i, lit)| (Point::new(i as i32 % width, i as i32 / width), lit))
+    }
+}
+
+fn bit(pbm: bool, c: char) -> Option<bool> {
+    match (pbm, c) {
+        (true, '1') | (false, '#') => Some(true),
+        (true, '0') | (false, '.') => Some(false),
+        _ => None,
+    }
+}
+
+fn skip_comments(mut s: &str) -> &str {
+    loop {
+        s = s.trim_start();
+        match s.strip_prefix('#') {
+            Some(comment) => s = comment.split_once('\n').map_or("", |(_, rest)| rest),
+            None => return s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::graphics::VarDisplay;
+    use std::format;
+
+    #[test]
+    fn ascii_and_pbm() {
+        let mut buffer = [0b0100_0000u8, 0b1010_0000];
+        let display = VarDisplay::new(3, 2, &mut buffer);
+        assert_display_eq!(display, ".#.\n#.#\n");
+        assert_display_eq!(display, "P1\n# comment\n3 2\n0 1 0\n1 0 1\n");
+    }
+
+    #[test]
+    fn mismatch_output() {
+        let mut buffer = [0b0100_0000u8, 0b1010_0000];
+        let mut display = VarDisplay::new(3, 2, &mut buffer);
+        display
+            .draw_helper(3, 2, Pixel(Point::new(1, 1), Color::Green))
+            .unwrap();
+        display
+            .draw_helper(3, 2, Pixel(Point::new(0, 1), Color::Dark))
+            .unwrap();
+        let mismatch = compare(&display, ".#.\n#.#").unwrap_err();
+        assert_eq!(
+            format!("{}", mismatch),
+            "expected   actual\n.#.   .#.\n#.#   -+#\n"
+        );
+        assert!(matches!(
+            compare(&display, "..\n.."),
+            Err(Mismatch::Size { .. })
+        ));
+        assert!(matches!(
+            compare(&display, "P1 3 2 0 1"),
+            Err(Mismatch::Invalid)
+        ));
+    }
+}
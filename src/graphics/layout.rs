@@ -0,0 +1,174 @@
+//! How pixels are packed into the bytes of a buffer
+//!
+//! The GP1287 takes its GRAM row by row, eight pixels per byte with the msb first ([RowMajor]).
+//! Other controllers expect the same bits organized differently, so the displays are generic
+//! over a [PixelLayout] and the buffer can be sent as is.
+//!
+//! All coordinates are unrotated pixels of the buffer. A layout works with a `stride`,
+//! the number of bytes between two consecutive lines of its memory map.
+
+use crate::color::Color;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Memory map of a monochrome buffer
+pub trait PixelLayout {
+    /// Stride of a buffer holding exactly `width` x `height` pixels
+    fn stride(width: u32, height: u32) -> usize;
+
+    /// Returns true if `width` x `height` pixels can be addressed with `stride`
+    fn fits(width: u32, height: u32, stride: usize) -> bool;
+
+    /// Number of bytes needed for `width` x `height` pixels with `stride`
+    fn buffer_len(width: u32, height: u32, stride: usize) -> usize;
+
+    /// Index of the byte and mask of the bit holding pixel (`x`, `y`)
+    fn position(x: u32, y: u32, stride: usize) -> (usize, u8);
+
+    /// Sets all pixels of `area` to `color`
+    ///
+    /// The default sets one pixel after the other, layouts override it to write whole bytes.
+    fn fill(buffer: &mut [u8], stride: usize, area: &Rectangle, color: Color) {
+        for point in area.points() {
+            let (index, bit) = Self::position(point.x as u32, point.y as u32, stride);
+            set_bit(&mut buffer[index], bit, color);
+        }
+    }
+}
+
+/// One line of bytes per pixel row, the msb is the left most pixel
+///
+/// This is the layout of the GP1287 GRAM and the default of all displays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RowMajor;
+
+/// One line of bytes per pixel column, the msb is the top most pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColumnMajor;
+
+/// Pages of eight pixel rows with one byte per column, the lsb is the top most pixel
+///
+/// Used by most graphic LCD and OLED controllers, e.g. the SSD1306.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerticalPages;
+
+impl PixelLayout for RowMajor {
+    fn stride(width: u32, _height: u32) -> usize {
+        width.div_ceil(8) as usize
+    }
+
+    fn fits(width: u32, _height: u32, stride: usize) -> bool {
+        width as usize <= stride * 8
+    }
+
+    fn buffer_len(_width: u32, height: u32, stride: usize) -> usize {
+        height as usize * stride
+    }
+
+    fn position(x: u32, y: u32, stride: usize) -> (usize, u8) {
+        (y as usize * stride + x as usize / 8, 0x80 >> (x % 8))
+    }
+
+    fn fill(buffer: &mut [u8], stride: usize, area: &Rectangle, color: Color) {
+        let Some(bottom_right) = area.bottom_right() else {
+            return;
+        };
+        let (left, right) = (area.top_left.x as usize, bottom_right.x as usize);
+        let (first, last) = (left / 8, right / 8);
+        // bits of the first and last byte inside of the area
+        let head = 0xff >> (left % 8);
+        let tail = 0xff << (7 - right % 8);
+        for y in area.top_left.y as usize..=bottom_right.y as usize {
+            let row = &mut buffer[y * stride..];
+            if first == last {
+                set_bit(&mut row[first], head & tail, color);
+                continue;
+            }
+            set_bit(&mut row[first], head, color);
+            row[first + 1..last].fill(color.get_byte_value());
+            set_bit(&mut row[last], tail, color);
+        }
+    }
+}
+
+impl PixelLayout for ColumnMajor {
+    fn stride(_width: u32, height: u32) -> usize {
+        height.div_ceil(8) as usize
+    }
+
+    fn fits(_width: u32, height: u32, stride: usize) -> bool {
+        height as usize <= stride * 8
+    }
+
+    fn buffer_len(width: u32, _height: u32, stride: usize) -> usize {
+        width as usize * stride
+    }
+
+    fn position(x: u32, y: u32, stride: usize) -> (usize, u8) {
+        (x as usize * stride + y as usize / 8, 0x80 >> (y % 8))
+    }
+}
+
+impl PixelLayout for VerticalPages {
+    fn stride(width: u32, _height: u32) -> usize {
+        width as usize
+    }
+
+    fn fits(width: u32, _height: u32, stride: usize) -> bool {
+        width as usize <= stride
+    }
+
+    fn buffer_len(_width: u32, height: u32, stride: usize) -> usize {
+        height.div_ceil(8) as usize * stride
+    }
+
+    fn position(x: u32, y: u32, stride: usize) -> (usize, u8) {
+        ((y / 8) as usize * stride + x as usize, 1 << (y % 8))
+    }
+}
+
+fn set_bit(byte: &mut u8, bit: u8, color: Color) {
+    match color {
+        Color::Dark => *byte &= !bit,
+        Color::Green => *byte |= bit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // fills `area` with the fast path and pixel by pixel, both have to agree
+    fn check_fill<L: PixelLayout>(width: u32, height: u32, area: Rectangle) {
+        let stride = L::stride(width, height);
+        let len = L::buffer_len(width, height, stride);
+        for color in [Color::Green, Color::Dark] {
+            let mut fast = [!color.get_byte_value(); 64];
+            let mut slow = fast;
+            L::fill(&mut fast[..len], stride, &area, color);
+            for point in area.points() {
+                let (index, bit) = L::position(point.x as u32, point.y as u32, stride);
+                set_bit(&mut slow[index], bit, color);
+            }
+            assert_eq!(fast, slow, "{:?}", area);
+        }
+    }
+
+    #[test]
+    fn row_major_fill_matches_pixels() {
+        for (x, y, w, h) in [(0, 0, 20, 5), (3, 1, 2, 3), (5, 2, 14, 1), (8, 0, 8, 5)] {
+            let area = Rectangle::new(Point::new(x, y), Size::new(w, h));
+            check_fill::<RowMajor>(20, 5, area);
+        }
+        check_fill::<RowMajor>(20, 5, Rectangle::zero());
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(RowMajor::position(9, 2, 3), (7, 0x40));
+        assert_eq!(ColumnMajor::position(9, 2, 3), (27, 0x20));
+        assert_eq!(VerticalPages::position(9, 10, 16), (25, 0x04));
+        assert_eq!(VerticalPages::buffer_len(16, 9, 16), 32);
+        assert!(!ColumnMajor::fits(100, 25, 3));
+    }
+}
@@ -1,6 +1,89 @@
 use crate::traits::Command;
 use embedded_hal::{delay::DelayNs, digital::*, spi::Operation, spi::SpiDevice};
 
+/// Maximum number of chunks sent in one transaction with [TransferMode::Chunks]
+pub const MAX_CHUNKS: usize = 16;
+
+/// How large writes like whole frames are put on the bus
+///
+/// Some HALs can't handle long writes, e.g. because of the length limit of their DMA.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransferMode {
+    /// All data in a single write
+    #[default]
+    Single,
+    /// The data is split into writes of at most this many bytes, all inside one transaction
+    ///
+    /// The arguments of a command, at most 7 bytes, are always written in one piece.
+    ///
+    /// Transactions with more than [MAX_CHUNKS] chunks are split like with
+    /// [TransferMode::Transactions].
+    Chunks(usize),
+    /// Every transaction carries at most this many bytes of data
+    ///
+    /// GRAM writes are split into several commands, each addressing its own part of the GRAM.
+    /// At least one buffer row is sent per transaction.
+    Transactions(usize),
+}
+
+/// Bytes of data which are bit reversed at once for an LSB first bus without DMA buffer
+const REVERSE_CHUNK: usize = 64;
+
+/// Reversed pieces in one transaction, enough for a whole GP1287 frame
+const REVERSE_PIECES: usize = 28;
+
+/// Order in which the SPI peripheral shifts out the bits of a byte
+///
+/// The controller takes command bytes LSB first, but arguments and pixel data MSB first.
+/// The driver reverses whatever doesn't match the bus.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Most significant bit first, the default of almost every SPI peripheral
+    #[default]
+    MsbFirst,
+    /// Least significant bit first
+    LsbFirst,
+}
+
+impl BitOrder {
+    fn command(self, address: u8) -> u8 {
+        match self {
+            BitOrder::MsbFirst => address.reverse_bits(),
+            BitOrder::LsbFirst => address,
+        }
+    }
+
+    // arguments as they have to be written to the bus, the longest are 7 bytes
+    fn args<'a>(self, bytes: &'a [u8], reversed: &'a mut [u8; 8]) -> &'a [u8] {
+        match self {
+            BitOrder::MsbFirst => bytes,
+            BitOrder::LsbFirst => {
+                let reversed = &mut reversed[..bytes.len()];
+                reverse_into(reversed, bytes);
+                reversed
+            }
+        }
+    }
+}
+
+/// Bit reversed value of every byte
+static REVERSED: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = (i as u8).reverse_bits();
+        i += 1;
+    }
+    table
+};
+
+/// Copies `bytes` into `out` with the bits of every byte reversed
+fn reverse_into(out: &mut [u8], bytes: &[u8]) {
+    for (out, byte) in out.iter_mut().zip(bytes) {
+        *out = REVERSED[*byte as usize];
+    }
+}
+
 /// The Connection Interface of all (?) EEI VFD
 ///
 pub(crate) struct DisplayInterface<SPI, RST, DELAY> {
@@ -10,6 +93,12 @@ pub(crate) struct DisplayInterface<SPI, RST, DELAY> {
     pub(crate) delay: DELAY,
     /// Pin for Resetting
     rst: RST,
+    /// Splitting of large writes
+    pub(crate) transfer: TransferMode,
+    /// Staging buffer every transaction is copied to before sending
+    pub(crate) dma_buffer: Option<&'static mut [u8]>,
+    /// Bit order of the bus
+    pub(crate) bit_order: BitOrder,
 }
 
 impl<SPI, RST, DELAY> DisplayInterface<SPI, RST, DELAY>
@@ -23,6 +112,30 @@ where
             spi,
             delay,
             rst,
+            transfer: TransferMode::default(),
+            dma_buffer: None,
+            bit_order: BitOrder::default(),
+        }
+    }
+
+    /// Maximum number of data bytes which can be sent with a command taking `args` bytes
+    ///
+    /// Larger writes have to be split into several commands by the caller.
+    pub(crate) fn max_data(&self, args: usize) -> Option<usize> {
+        let transfer = match self.transfer {
+            TransferMode::Single => None,
+            TransferMode::Chunks(size) => Some(size * MAX_CHUNKS),
+            TransferMode::Transactions(size) => Some(size),
+        };
+        let staged = match (&self.dma_buffer, self.bit_order) {
+            (Some(buffer), _) => Some(buffer.len().saturating_sub(1 + args)),
+            // reversed in pieces on the stack
+            (None, BitOrder::LsbFirst) => Some(REVERSE_CHUNK * REVERSE_PIECES),
+            (None, BitOrder::MsbFirst) => None,
+        };
+        match (transfer, staged) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
         }
     }
 
@@ -36,11 +149,41 @@ where
         data: &[u8],
     ) -> Result<(), SPI::Error> {
         self.spi.write(&[])?;
-        self.spi.transaction(&mut [
-            Operation::Write(&[command.address().reverse_bits()]),
-            Operation::Write(args),
-            Operation::Write(data),
-        ])
+        let command = self.bit_order.command(command.address());
+        if let Some(buffer) = self.dma_buffer.as_deref_mut() {
+            // more data than the buffer holds goes into further transactions
+            let room = buffer.len().saturating_sub(1 + args.len()).max(1);
+            for data in parts(data, room) {
+                let len = stage(buffer, self.bit_order, command, args, data);
+                match self.transfer {
+                    TransferMode::Chunks(size) => {
+                        let (header, data) = buffer[..len].split_at(1 + args.len());
+                        let (command, args) = header.split_at(1);
+                        write_chunks(&mut self.spi, command, args, data, size)?
+                    }
+                    _ => self.spi.write(&buffer[..len])?,
+                }
+            }
+            return Ok(());
+        }
+        let mut reversed = [0; 8];
+        let args = self.bit_order.args(args, &mut reversed);
+        match (self.bit_order, self.transfer) {
+            (BitOrder::LsbFirst, TransferMode::Chunks(size)) => {
+                write_reversed(&mut self.spi, &[command], args, data, size)
+            }
+            (BitOrder::LsbFirst, _) => {
+                write_reversed(&mut self.spi, &[command], args, data, REVERSE_CHUNK)
+            }
+            (BitOrder::MsbFirst, TransferMode::Chunks(size)) => {
+                write_chunks(&mut self.spi, &[command], args, data, size)
+            }
+            (BitOrder::MsbFirst, _) => self.spi.transaction(&mut [
+                Operation::Write(&[command]),
+                Operation::Write(args),
+                Operation::Write(data),
+            ]),
+        }
     }
 
     /// Basic function for sending [Commands](Command) and the data belonging to it.
@@ -54,19 +197,28 @@ where
         // this is nessessary for shifting out the previous frame when communicating
         // with high frequency
         self.spi.write(&[])?;
-        self.spi.transaction(&mut [
-            Operation::Write(&[command.address().reverse_bits()]),
-            Operation::Write(args),
-        ])
+        let command = self.bit_order.command(command.address());
+        if let Some(buffer) = self.dma_buffer.as_deref_mut() {
+            let len = stage(buffer, self.bit_order, command, args, &[]);
+            return self.spi.write(&buffer[..len]);
+        }
+        let mut reversed = [0; 8];
+        let args = self.bit_order.args(args, &mut reversed);
+        self.spi
+            .transaction(&mut [Operation::Write(&[command]), Operation::Write(args)])
     }
 
-    /// Basic function for sending the same byte of data (one u8) multiple times over spi
+    /// Basic function for sending a [Command] followed by the same byte of data (one u8) R times
     ///
-    /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
-    #[allow(unused)]
-    pub(crate) fn data_x_times<const R: usize>(&mut self, val: u8) -> Result<(), SPI::Error> {
-        // Transfer data (u8) over spi
-        self.spi.write(&[val; R])
+    /// The data has to be part of the transaction of the command, the controller would read
+    /// it as a new command otherwise.
+    pub(crate) fn data_x_times<const R: usize, T: Command>(
+        &mut self,
+        command: T,
+        args: &[u8],
+        val: u8,
+    ) -> Result<(), SPI::Error> {
+        self.cmd_with_data(command, args, &[val; R])
     }
 
     /// Resets the device.
@@ -83,3 +235,161 @@ where
         self.delay.delay_ms(1)
     }
 }
+
+/// Copies a whole transaction into the staging buffer, returns its length
+///
+/// # Panics
+///
+/// If the buffer is too small for the whole transaction, callers split the data.
+fn stage(buffer: &mut [u8], order: BitOrder, command: u8, args: &[u8], data: &[u8]) -> usize {
+    let len = 1 + args.len() + data.len();
+    assert!(
+        len <= buffer.len(),
+        "DMA buffer too small for {} bytes",
+        len
+    );
+    buffer[0] = command;
+    let (args_out, data_out) = buffer[1..len].split_at_mut(args.len());
+    match order {
+        BitOrder::MsbFirst => {
+            args_out.copy_from_slice(args);
+            data_out.copy_from_slice(data);
+        }
+        BitOrder::LsbFirst => {
+            reverse_into(args_out, args);
+            reverse_into(data_out, data);
+        }
+    }
+    len
+}
+
+/// `data` in parts of at most `len` bytes, a single empty part if there is no data
+fn parts(data: &[u8], len: usize) -> impl Iterator<Item = &[u8]> {
+    let empty = data.is_empty().then_some(data);
+    data.chunks(len.max(1)).chain(empty)
+}
+
+/// Sends `data` in writes of at most `size` bytes inside a single transaction
+///
+/// Data for more than [MAX_CHUNKS] writes is sent in further transactions, each starting
+/// with `command` and `args` again. The driver splits GRAM writes before, so every
+/// transaction addresses its own part of the GRAM.
+fn write_chunks<SPI: SpiDevice>(
+    spi: &mut SPI,
+    command: &[u8],
+    args: &[u8],
+    data: &[u8],
+    size: usize,
+) -> Result<(), SPI::Error> {
+    let size = size.max(1);
+    let mut operations: [Operation<'_, u8>; MAX_CHUNKS + 2] =
+        core::array::from_fn(|_| Operation::Write(&[]));
+    for part in parts(data, size.saturating_mul(MAX_CHUNKS)) {
+        operations[0] = Operation::Write(command);
+        operations[1] = Operation::Write(args);
+        let mut len = 2;
+        for chunk in part.chunks(size) {
+            operations[len] = Operation::Write(chunk);
+            len += 1;
+        }
+        spi.transaction(&mut operations[..len])?;
+    }
+    Ok(())
+}
+
+/// Sends `data` bit reversed, in writes of at most `size` bytes inside a single transaction
+///
+/// The data is reversed in pieces of up to [REVERSE_CHUNK] bytes. Data for more than
+/// [REVERSE_PIECES] pieces is sent in further transactions like with [write_chunks].
+fn write_reversed<SPI: SpiDevice>(
+    spi: &mut SPI,
+    command: &[u8],
+    args: &[u8],
+    data: &[u8],
+    size: usize,
+) -> Result<(), SPI::Error> {
+    let size = size.clamp(1, REVERSE_CHUNK);
+    let mut reversed = [[0; REVERSE_CHUNK]; REVERSE_PIECES];
+    for part in parts(data, size * REVERSE_PIECES) {
+        for (out, piece) in reversed.iter_mut().zip(part.chunks(size)) {
+            reverse_into(out, piece);
+        }
+        let mut operations: [Operation<'_, u8>; REVERSE_PIECES + 2] =
+            core::array::from_fn(|_| Operation::Write(&[]));
+        operations[0] = Operation::Write(command);
+        operations[1] = Operation::Write(args);
+        let mut len = 2;
+        for (out, piece) in reversed.iter().zip(part.chunks(size)) {
+            operations[len] = Operation::Write(&out[..piece.len()]);
+            len += 1;
+        }
+        spi.transaction(&mut operations[..len])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::spi::ErrorType;
+    use std::vec::Vec;
+
+    // keeps the writes of every transaction
+    #[derive(Default)]
+    struct Recorder(Vec<Vec<Vec<u8>>>);
+
+    impl ErrorType for Recorder {
+        type Error = core::convert::Infallible;
+    }
+
+    impl SpiDevice for Recorder {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+            let writes = operations.iter().map(|operation| match operation {
+                Operation::Write(bytes) => bytes.to_vec(),
+                _ => Vec::new(),
+            });
+            self.0.push(writes.collect());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn chunks_beyond_one_transaction() {
+        let mut spi = Recorder::default();
+        let data: Vec<u8> = (0..40).collect();
+        write_chunks(&mut spi, &[0x10], &[1, 2], &data, 2).unwrap();
+        assert_eq!(spi.0.len(), 2);
+        assert_eq!(spi.0[0].len(), 2 + MAX_CHUNKS);
+        assert_eq!(spi.0[1][..2], [std::vec![0x10], std::vec![1, 2]]);
+        let sent: Vec<u8> = spi.0.iter().flat_map(|t| t[2..].concat()).collect();
+        assert_eq!(sent, data);
+
+        // a command without data is still sent
+        let mut spi = Recorder::default();
+        write_chunks(&mut spi, &[0x10], &[1, 2], &[], 2).unwrap();
+        assert_eq!(spi.0, [[std::vec![0x10], std::vec![1, 2]]]);
+    }
+
+    #[test]
+    fn reversed_in_pieces() {
+        let mut spi = Recorder::default();
+        let data: Vec<u8> = (0..=255)
+            .cycle()
+            .take(REVERSE_CHUNK * REVERSE_PIECES)
+            .collect();
+        write_reversed(&mut spi, &[0x10], &[1, 2], &data, REVERSE_CHUNK).unwrap();
+        assert_eq!(spi.0.len(), 1);
+        assert_eq!(spi.0[0].len(), 2 + REVERSE_PIECES);
+        let sent: Vec<u8> = spi.0[0][2..].concat();
+        assert!(sent.iter().zip(&data).all(|(a, b)| *a == b.reverse_bits()));
+
+        // longer than one transaction holds, split like chunks
+        let mut spi = Recorder::default();
+        write_reversed(&mut spi, &[0x10], &[1, 2], &data[..100], 3).unwrap();
+        assert_eq!(spi.0.len(), 2);
+        assert_eq!(spi.0[1][..2], [std::vec![0x10], std::vec![1, 2]]);
+        let sent: Vec<u8> = spi.0.iter().flat_map(|t| t[2..].concat()).collect();
+        assert!(sent.iter().zip(&data).all(|(a, b)| *a == b.reverse_bits()));
+        assert_eq!(sent.len(), 100);
+    }
+}
@@ -9,18 +9,43 @@
 #![no_std]
 #![deny(missing_docs)]
 
+#[cfg(test)]
+extern crate std;
+
 #[cfg(feature = "graphics")]
 pub mod graphics;
 
+#[cfg(feature = "graphics")]
+pub mod widgets;
+
 mod traits;
 
 pub mod color;
 
+pub mod codec;
+
+pub mod animation;
+
+pub mod self_test;
+
+pub mod power;
+
+#[cfg(feature = "graphics")]
+pub mod mirror;
+
 /// Interface for the physical connection between display and the controlling device
 mod interface;
 
+pub use interface::{BitOrder, TransferMode, MAX_CHUNKS};
+
 pub mod gp1287bi;
 
+#[cfg(any(test, feature = "model"))]
+pub mod model;
+
+#[cfg(any(test, feature = "soft-spi"))]
+pub mod soft_spi;
+
 /// Includes everything important besides the chosen Display
 pub mod prelude {
     pub use crate::color::Color;
@@ -42,7 +67,7 @@ pub mod prelude {
 /// \[XXXXX210\]\[76543210\]...\[76543210\] | height
 /// \[XXXXX210\]\[76543210\]...\[76543210\] v
 pub const fn buffer_len(width: usize, height: usize) -> usize {
-    (width + 7) / 8 * height
+    width.div_ceil(8) * height
 }
 
 use embedded_hal::spi::{Mode, Phase, Polarity};
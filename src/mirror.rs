@@ -0,0 +1,366 @@
+//! Showing one frame on several displays
+//!
+//! [Mirror] pushes the buffer of a single [Display] to a number of drivers, e.g. a customer
+//! and an operator facing display. Each target gets its own [Transform] and brightness,
+//! applied while flushing, so the frame is only drawn once. A failing target doesn't keep
+//! the frame from the others, the errors are collected in a [MirrorReport].
+//!
+//! ```ignore
+//! let mirror = Mirror::new([
+//!     MirrorTarget::default(),
+//!     MirrorTarget { transform: Transform::Rotate180, brightness: Some(0x100) },
+//! ]);
+//! let report = mirror.present(&display, &mut [customer, operator], &mut scratch);
+//! ```
+
+use embedded_graphics_core::prelude::*;
+use embedded_hal::{delay::DelayNs, digital::*, spi::SpiDevice};
+
+use crate::graphics::{LayoutDisplay, PixelLayout, RowMajor};
+use crate::traits::EEIDisplay;
+
+/// How the frame is changed for one target
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transform {
+    /// Sent as drawn
+    #[default]
+    Identity,
+    /// Upside down, for a panel mounted the other way around
+    Rotate180,
+    /// Mirrored along the buffer rows
+    FlipHorizontal,
+    /// Mirrored along the buffer columns
+    FlipVertical,
+}
+
+impl Transform {
+    // source pixel shown at (`x`, `y`) of a `width` x `height` buffer
+    fn source(self, x: u32, y: u32, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Transform::Identity => (x, y),
+            Transform::Rotate180 => (width - 1 - x, height - 1 - y),
+            Transform::FlipHorizontal => (width - 1 - x, y),
+            Transform::FlipVertical => (x, height - 1 - y),
+        }
+    }
+}
+
+/// Settings of one display fed by a [Mirror]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MirrorTarget {
+    /// Applied to the frame before it is sent
+    pub transform: Transform,
+    /// Set before each frame, None leaves the brightness alone
+    pub brightness: Option<u32>,
+}
+
+/// Errors of one target of a [Mirror]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MirrorError<E> {
+    /// The scratch buffer can't hold the transformed frame, nothing was sent
+    Scratch {
+        /// Length of the frame
+        required: usize,
+        /// Length of the scratch buffer
+        actual: usize,
+    },
+    /// Setting the brightness failed, the frame was sent anyway
+    Brightness(E),
+    /// Writing the frame failed
+    Spi(E),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for MirrorError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MirrorError::Scratch { required, actual } => write!(
+                f,
+                "Scratch too small: {} bytes needed, {} given",
+                required, actual
+            ),
+            MirrorError::Brightness(e) => write!(f, "Setting the brightness failed: {}", e),
+            MirrorError::Spi(e) => write!(f, "Writing failed: {}", e),
+        }
+    }
+}
+
+/// Errors of the targets of one [Mirror::present], by index
+#[derive(Debug)]
+pub struct MirrorReport<E, const N: usize> {
+    errors: [Option<E>; N],
+}
+
+impl<E, const N: usize> MirrorReport<E, N> {
+    /// True if every target got the frame
+    pub fn is_ok(&self) -> bool {
+        self.errors.iter().all(Option::is_none)
+    }
+
+    /// The error of target `index`, if it failed
+    pub fn error(&self, index: usize) -> Option<&E> {
+        self.errors.get(index)?.as_ref()
+    }
+
+    /// Indices and errors of the failed targets
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &E)> + '_ {
+        self.errors
+            .iter()
+            .enumerate()
+            .filter_map(|(index, error)| Some((index, error.as_ref()?)))
+    }
+}
+
+/// Fans one frame out to `N` displays
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mirror<const N: usize> {
+    targets: [MirrorTarget; N],
+}
+
+impl<const N: usize> Mirror<N> {
+    /// Creates a mirror with the settings of each target
+    pub fn new(targets: [MirrorTarget; N]) -> Self {
+        Mirror { targets }
+    }
+
+    /// Returns the settings of target `index`
+    pub fn target(&self, index: usize) -> &MirrorTarget {
+        &self.targets[index]
+    }
+
+    /// Returns the settings of target `index` for changing them
+    pub fn target_mut(&mut self, index: usize) -> &mut MirrorTarget {
+        &mut self.targets[index]
+    }
+
+    /// Sends the frame of `display` to all `targets`, in the order of the settings
+    ///
+    /// `scratch` holds the transformed frames and needs to be as large as the buffer
+    /// of `display`, unless all targets use [Transform::Identity]. Targets it is too
+    /// small for fail with [MirrorError::Scratch].
+    pub fn present<SPI, RST, DELAY, D, T>(
+        &self,
+        display: &D,
+        targets: &mut [T; N],
+        scratch: &mut [u8],
+    ) -> MirrorReport<MirrorError<SPI::Error>, N>
+    where
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        D: LayoutDisplay<Layout = RowMajor>,
+        T: EEIDisplay<SPI, RST, DELAY>,
+    {
+        let mut report = MirrorReport {
+            errors: core::array::from_fn(|_| None),
+        };
+        for (index, target) in targets.iter_mut().enumerate() {
+            report.errors[index] = self.present_to(index, display, target, scratch).err();
+        }
+        report
+    }
+
+    /// Sends the frame of `display` to the single target `index`
+    ///
+    /// For targets of different types, e.g. on different buses. See [Mirror::present].
+    ///
+    /// A failed brightness doesn't keep the frame from the target, it is returned as
+    /// [MirrorError::Brightness] if the frame got through.
+    pub fn present_to<SPI, RST, DELAY, D, T>(
+        &self,
+        index: usize,
+        display: &D,
+        target: &mut T,
+        scratch: &mut [u8],
+    ) -> Result<(), MirrorError<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        D: LayoutDisplay<Layout = RowMajor>,
+        T: EEIDisplay<SPI, RST, DELAY>,
+    {
+        let settings = self.targets[index];
+        let buffer = display.buffer();
+        let frame = if settings.transform == Transform::Identity {
+            buffer
+        } else {
+            let actual = scratch.len();
+            let scratch = scratch
+                .get_mut(..buffer.len())
+                .ok_or(MirrorError::Scratch {
+                    required: buffer.len(),
+                    actual,
+                })?;
+            transform(
+                settings.transform,
+                buffer,
+                scratch,
+                display.bounding_box().size,
+            );
+            scratch
+        };
+        let brightness = match settings.brightness {
+            Some(brightness) => target.set_brightness(brightness),
+            None => Ok(()),
+        };
+        target.update_frame(frame).map_err(MirrorError::Spi)?;
+        brightness.map_err(MirrorError::Brightness)
+    }
+}
+
+// writes `buffer` with `transform` applied into `out`, both row major with `size` pixels
+fn transform(transform: Transform, buffer: &[u8], out: &mut [u8], size: Size) {
+    let Size { width, height } = size;
+    let stride = RowMajor::stride(width, height);
+    out.fill(0);
+    for y in 0..height {
+        for x in 0..width {
+            let (sx, sy) = transform.source(x, y, width, height);
+            let (index, bit) = RowMajor::position(sx, sy, stride);
+            if buffer[index] & bit != 0 {
+                let (index, bit) = RowMajor::position(x, y, stride);
+                out[index] |= bit;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::gp1287bi::{Display256x50, VFD256x50};
+    use crate::graphics::{Display, DisplayRotation};
+    use crate::model::{Gp1287Model, ModelError, NoopDelay, NoopPin};
+    use core::cell::Cell;
+    use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Rectangle};
+    use embedded_graphics::Drawable;
+    use embedded_hal::spi::{ErrorType, Operation};
+
+    #[test]
+    fn transforms() {
+        let size = Size::new(12, 3);
+        let buffer = [0b1100_0000, 0b0001_0000, 0, 0, 0, 0];
+        let mut out = [0u8; 6];
+        transform(Transform::Rotate180, &buffer, &mut out, size);
+        assert_eq!(out, [0, 0, 0, 0, 0b1000_0000, 0b0011_0000]);
+        transform(Transform::FlipHorizontal, &buffer, &mut out, size);
+        assert_eq!(&out[..2], &[0b1000_0000, 0b0011_0000]);
+        transform(Transform::FlipVertical, &buffer, &mut out, size);
+        assert_eq!(&out[4..], &buffer[..2]);
+    }
+
+    #[test]
+    fn fans_out() {
+        let mut display = Display256x50::default();
+        Rectangle::new(Point::new(3, 10), Size::new(20, 30))
+            .into_styled(PrimitiveStyle::with_fill(Color::Green))
+            .draw(&mut display)
+            .unwrap();
+        let mut flipped = Display256x50::default();
+        flipped.set_rotation(DisplayRotation::Rotate180);
+        Rectangle::new(Point::new(3, 10), Size::new(20, 30))
+            .into_styled(PrimitiveStyle::with_fill(Color::Green))
+            .draw(&mut flipped)
+            .unwrap();
+
+        let mirror = Mirror::new([
+            MirrorTarget::default(),
+            MirrorTarget {
+                transform: Transform::Rotate180,
+                brightness: Some(0x123),
+            },
+        ]);
+        let (mut customer, mut operator) = (Gp1287Model::new(), Gp1287Model::new());
+        let mut scratch = [0u8; 1792];
+        let mut targets = [
+            VFD256x50::new(&mut customer, NoopPin, NoopDelay).unwrap(),
+            VFD256x50::new(&mut operator, NoopPin, NoopDelay).unwrap(),
+        ];
+        let report = mirror.present(&display, &mut targets, &mut scratch);
+        assert!(report.is_ok());
+        assert!(customer.frame()[..] == *display.buffer());
+        assert!(operator.frame()[..] == *flipped.buffer());
+        assert_eq!(operator.brightness(), 0x123);
+        assert_eq!(customer.brightness(), 0x30);
+
+        // the first target fails, the second still gets the frame
+        let (mut customer, mut operator) = (Gp1287Model::new(), Gp1287Model::new());
+        let mut targets = [
+            VFD256x50::new(&mut customer, NoopPin, NoopDelay).unwrap(),
+            VFD256x50::new(&mut operator, NoopPin, NoopDelay).unwrap(),
+        ];
+        targets[0].sleep().unwrap();
+        let report = mirror.present(&display, &mut targets, &mut scratch);
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.error(0),
+            Some(&MirrorError::Spi(ModelError::Asleep("WriteGRAM")))
+        );
+        assert_eq!(report.errors().count(), 1);
+        assert!(operator.frame()[..] == *flipped.buffer());
+
+        // a short scratch only fails the transformed target
+        let (mut customer, mut operator) = (Gp1287Model::new(), Gp1287Model::new());
+        let mut targets = [
+            VFD256x50::new(&mut customer, NoopPin, NoopDelay).unwrap(),
+            VFD256x50::new(&mut operator, NoopPin, NoopDelay).unwrap(),
+        ];
+        let report = mirror.present(&display, &mut targets, &mut scratch[..100]);
+        assert_eq!(report.error(0), None);
+        assert_eq!(
+            report.error(1),
+            Some(&MirrorError::Scratch {
+                required: 1792,
+                actual: 100
+            })
+        );
+        assert!(customer.frame()[..] == *display.buffer());
+    }
+
+    // fails the next `failures` transactions
+    struct Flaky<'a> {
+        model: &'a mut Gp1287Model,
+        failures: &'a Cell<u32>,
+    }
+
+    impl ErrorType for Flaky<'_> {
+        type Error = ModelError;
+    }
+
+    impl SpiDevice for Flaky<'_> {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ModelError> {
+            match self.failures.get() {
+                0 => self.model.transaction(operations),
+                n => {
+                    self.failures.set(n - 1);
+                    Err(ModelError::UnsupportedOperation)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sends_frame_after_failed_brightness() {
+        let mut display = Display256x50::default();
+        display.clear_buffer(Color::Green);
+        let mirror = Mirror::new([MirrorTarget {
+            transform: Transform::Identity,
+            brightness: Some(0x123),
+        }]);
+        let mut model = Gp1287Model::new();
+        let failures = Cell::new(0);
+        let spi = Flaky {
+            model: &mut model,
+            failures: &failures,
+        };
+        let mut targets = [VFD256x50::new(spi, NoopPin, NoopDelay).unwrap()];
+        failures.set(1);
+        let report = mirror.present(&display, &mut targets, &mut []);
+        assert_eq!(
+            report.error(0),
+            Some(&MirrorError::Brightness(ModelError::UnsupportedOperation))
+        );
+        assert!(model.frame()[..] == *display.buffer());
+    }
+}
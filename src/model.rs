@@ -0,0 +1,539 @@
+//! Software model of the GP1287BI controller for host side tests
+//!
+//! [Gp1287Model] implements [SpiDevice] and decodes what the driver sends the same way
+//! the controller does: bit reversed command bytes, followed by the arguments and data.
+//! The bus is MSB first unless created with [Gp1287Model::with_bit_order].
+//! It keeps track of the register state and of a simulated GRAM, so tests can check the
+//! resulting image instead of exact byte sequences.
+//!
+//! ```
+//! use eei_vfd::gp1287bi::VFD256x50;
+//! use eei_vfd::model::{Gp1287Model, NoopDelay, NoopPin};
+//! use eei_vfd::prelude::*;
+//!
+//! let mut model = Gp1287Model::new();
+//! let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+//! vfd.set_brightness(0x123).unwrap();
+//! assert_eq!(model.brightness(), 0x123);
+//! ```
+//!
+//! Only available with the `model` feature.
+
+use core::convert::Infallible;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::spi::{self, ErrorKind, Operation, SpiDevice};
+
+use crate::gp1287bi::command::Command;
+use crate::gp1287bi::{NUM_DISPLAY_BITS, ROW_BYTES};
+use crate::BitOrder;
+
+/// Number of columns in the GRAM
+pub const GRAM_COLUMNS: usize = 256;
+/// Number of pixel rows in each GRAM column
+pub const GRAM_ROWS: usize = 64;
+
+/// Malformed traffic detected by the model
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ModelError {
+    /// The (already bit reversed) byte is not a known command
+    UnknownCommand(u8),
+    /// The transaction ended before all arguments were sent
+    MissingArguments {
+        /// Name of the command
+        command: &'static str,
+        /// Number of argument bytes the command takes
+        expected: usize,
+        /// Number of argument bytes received
+        got: usize,
+    },
+    /// More bytes than the command takes were sent
+    TooManyArguments {
+        /// Name of the command
+        command: &'static str,
+        /// Number of argument bytes the command takes
+        expected: usize,
+    },
+    /// A GRAM write does not fit into the GRAM
+    GramOverflow {
+        /// Column the write went past
+        column: usize,
+        /// Row the write went past
+        row: usize,
+    },
+    /// A command other than wake up or reset was sent during sleep
+    Asleep(&'static str),
+    /// The model only understands write operations
+    UnsupportedOperation,
+    /// There is no display position with this number, only 1 and 2
+    NoSuchPosition(usize),
+}
+
+impl core::fmt::Display for ModelError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ModelError::UnknownCommand(byte) => write!(
+                f,
+                "Unknown command byte {:#04x} (address {:#04x})",
+                byte,
+                byte.reverse_bits()
+            ),
+            ModelError::MissingArguments {
+                command,
+                expected,
+                got,
+            } => write!(
+                f,
+                "{} expects {} argument bytes, got {}",
+                command, expected, got
+            ),
+            ModelError::TooManyArguments { command, expected } => write!(
+                f,
+                "{} expects {} argument bytes, got more",
+                command, expected
+            ),
+            ModelError::GramOverflow { column, row } => {
+                write!(f, "GRAM write past column {}, row {}", column, row)
+            }
+            ModelError::Asleep(command) => write!(f, "{} sent while asleep", command),
+            ModelError::UnsupportedOperation => write!(f, "Only write operations are supported"),
+            ModelError::NoSuchPosition(position) => {
+                write!(f, "There is no display position {}", position)
+            }
+        }
+    }
+}
+
+impl spi::Error for ModelError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Behavioral model of the GP1287BI
+pub struct Gp1287Model {
+    gram: [u64; GRAM_COLUMNS],
+    brightness: u16,
+    vfd_mode: [u8; 2],
+    display_area: [u8; 7],
+    internal_speed: [u8; 4],
+    position_offsets: [[u8; 2]; 2],
+    display_mode: u8,
+    frame_sync: u8,
+    oscillation: Option<u8>,
+    asleep: bool,
+    commands: usize,
+    largest_write: usize,
+    largest_transaction: usize,
+    bit_order: BitOrder,
+    last_error: Option<ModelError>,
+}
+
+impl Default for Gp1287Model {
+    fn default() -> Self {
+        Gp1287Model {
+            gram: [0; GRAM_COLUMNS],
+            brightness: 0,
+            vfd_mode: [0; 2],
+            display_area: [0; 7],
+            internal_speed: [0; 4],
+            position_offsets: [[0; 2]; 2],
+            display_mode: 0,
+            frame_sync: 0,
+            oscillation: None,
+            asleep: false,
+            commands: 0,
+            largest_write: 0,
+            largest_transaction: 0,
+            bit_order: BitOrder::MsbFirst,
+            last_error: None,
+        }
+    }
+}
+
+// decoding state of a single transaction
+#[derive(Default)]
+struct Parser {
+    command: Option<Command>,
+    args: [u8; 8],
+    len: usize,
+    column: usize,
+    row: usize,
+}
+
+impl Gp1287Model {
+    /// Creates a model in its power on state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a model behind a bus shifting out bytes in `bit_order`
+    pub fn with_bit_order(bit_order: BitOrder) -> Self {
+        Gp1287Model {
+            bit_order,
+            ..Self::default()
+        }
+    }
+
+    /// Current brightness, 10 bits
+    pub fn brightness(&self) -> u16 {
+        self.brightness
+    }
+
+    /// Arguments of the last VFD mode setting
+    pub fn vfd_mode(&self) -> [u8; 2] {
+        self.vfd_mode
+    }
+
+    /// Arguments of the last display area setting
+    pub fn display_area(&self) -> [u8; 7] {
+        self.display_area
+    }
+
+    /// Arguments of the last internal speed setting
+    pub fn internal_speed(&self) -> [u8; 4] {
+        self.internal_speed
+    }
+
+    /// Arguments of the last offset setting of display position 1 or 2
+    pub fn position_offset(&self, position: usize) -> Result<[u8; 2], ModelError> {
+        position
+            .checked_sub(1)
+            .and_then(|index| self.position_offsets.get(index))
+            .copied()
+            .ok_or(ModelError::NoSuchPosition(position))
+    }
+
+    /// Argument of the last display mode setting
+    pub fn display_mode(&self) -> u8 {
+        self.display_mode
+    }
+
+    /// Argument of the last frame sync setting
+    pub fn frame_sync(&self) -> u8 {
+        self.frame_sync
+    }
+
+    /// Argument of the last oscillation setting, if there was one
+    pub fn oscillation(&self) -> Option<u8> {
+        self.oscillation
+    }
+
+    /// True while the controller is in sleep mode
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    /// Number of commands received
+    pub fn commands(&self) -> usize {
+        self.commands
+    }
+
+    /// Length of the longest single write operation seen on the bus
+    pub fn largest_write(&self) -> usize {
+        self.largest_write
+    }
+
+    /// Number of bytes of the longest transaction seen on the bus
+    pub fn largest_transaction(&self) -> usize {
+        self.largest_transaction
+    }
+
+    /// The error of the last rejected transaction
+    pub fn last_error(&self) -> Option<ModelError> {
+        self.last_error
+    }
+
+    /// Returns a single GRAM pixel, None outside of the GRAM
+    pub fn pixel(&self, column: usize, row: usize) -> Option<bool> {
+        if row >= GRAM_ROWS {
+            return None;
+        }
+        Some(self.gram.get(column)? & (1 << row) != 0)
+    }
+
+    /// The visible image, in the buffer layout of [Display256x50](crate::gp1287bi::Display256x50)
+    ///
+    /// Every buffer row is one GRAM column, starting at the row given by the offset of display position 1.
+    pub fn frame(&self) -> [u8; NUM_DISPLAY_BITS as usize] {
+        let start = self.position_offsets[0][1] as usize;
+        let mut frame = [0u8; NUM_DISPLAY_BITS as usize];
+        for (column, row) in frame.chunks_mut(ROW_BYTES).enumerate() {
+            for (i, byte) in row.iter_mut().enumerate() {
+                for bit in 0..8 {
+                    let y = start + i * 8 + bit;
+                    if self.pixel(column, y) == Some(true) {
+                        *byte |= 0x80 >> bit;
+                    }
+                }
+            }
+        }
+        frame
+    }
+
+    fn feed(&mut self, parser: &mut Parser, byte: u8) -> Result<(), ModelError> {
+        let command = match parser.command {
+            Some(command) => command,
+            None => {
+                let command = Command::from_address(byte.reverse_bits())
+                    .ok_or(ModelError::UnknownCommand(byte))?;
+                if self.asleep && !matches!(command, Command::WakeUp | Command::Reset) {
+                    return Err(ModelError::Asleep(name(command)));
+                }
+                parser.command = Some(command);
+                return Ok(());
+            }
+        };
+        let expected = arg_count(command);
+        if parser.len < expected {
+            parser.args[parser.len] = byte;
+            parser.len += 1;
+            if command == Command::WriteGRAM && parser.len == expected {
+                parser.column = parser.args[0] as usize;
+                parser.row = parser.args[1] as usize;
+            }
+            return Ok(());
+        }
+        if command != Command::WriteGRAM {
+            return Err(ModelError::TooManyArguments {
+                command: name(command),
+                expected,
+            });
+        }
+
+        // pixel data, msb first, filling columns of args[2] + 1 pixels from row args[1]
+        let (first_row, last_row) = (
+            parser.args[1] as usize,
+            parser.args[1] as usize + parser.args[2] as usize,
+        );
+        for bit in (0..8).rev() {
+            if parser.column >= GRAM_COLUMNS || parser.row >= GRAM_ROWS {
+                return Err(ModelError::GramOverflow {
+                    column: parser.column,
+                    row: parser.row,
+                });
+            }
+            let mask = 1 << parser.row;
+            if byte & (1 << bit) != 0 {
+                self.gram[parser.column] |= mask;
+            } else {
+                self.gram[parser.column] &= !mask;
+            }
+            parser.row += 1;
+            if parser.row > last_row {
+                parser.row = first_row;
+                parser.column += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, parser: &Parser) -> Result<(), ModelError> {
+        let command = match parser.command {
+            Some(command) => command,
+            // empty transactions are used to flush the bus
+            None => return Ok(()),
+        };
+        let expected = arg_count(command);
+        if parser.len < expected {
+            return Err(ModelError::MissingArguments {
+                command: name(command),
+                expected,
+                got: parser.len,
+            });
+        }
+        let args = &parser.args;
+        self.commands += 1;
+        match command {
+            Command::Reset => {
+                *self = Gp1287Model {
+                    commands: self.commands,
+                    largest_write: self.largest_write,
+                    largest_transaction: self.largest_transaction,
+                    bit_order: self.bit_order,
+                    ..Gp1287Model::default()
+                }
+            }
+            Command::ClearGRAM => self.gram = [0; GRAM_COLUMNS],
+            Command::VFDModeSetting => self.vfd_mode.copy_from_slice(&args[..2]),
+            Command::DisplayAreaSetting => self.display_area.copy_from_slice(&args[..7]),
+            Command::InternalSpeedSetting => self.internal_speed.copy_from_slice(&args[..4]),
+            Command::BrightnessSetting => {
+                self.brightness = ((args[0] as u16 & 0b11) << 8) | args[1] as u16
+            }
+            Command::WriteGRAM => {}
+            Command::DisplayPosition1Offset => self.position_offsets[0].copy_from_slice(&args[..2]),
+            Command::DisplayPosition2Offset => self.position_offsets[1].copy_from_slice(&args[..2]),
+            Command::DisplayModeSetting => self.display_mode = args[0],
+            Command::FrameSyncSetting => self.frame_sync = args[0],
+            Command::OscillationSetting => self.oscillation = Some(args[0]),
+            Command::UnknownInit => {}
+            Command::WakeUp => self.asleep = false,
+            Command::Sleep => self.asleep = true,
+        }
+        Ok(())
+    }
+}
+
+fn arg_count(command: Command) -> usize {
+    match command {
+        Command::Reset | Command::ClearGRAM | Command::WakeUp | Command::Sleep => 0,
+        Command::VFDModeSetting => 2,
+        Command::DisplayAreaSetting => 7,
+        Command::InternalSpeedSetting => 4,
+        Command::BrightnessSetting => 2,
+        Command::WriteGRAM => 3,
+        Command::DisplayPosition1Offset | Command::DisplayPosition2Offset => 2,
+        Command::DisplayModeSetting
+        | Command::FrameSyncSetting
+        | Command::OscillationSetting
+        | Command::UnknownInit => 1,
+    }
+}
+
+fn name(command: Command) -> &'static str {
+    match command {
+        Command::Reset => "Reset",
+        Command::ClearGRAM => "ClearGRAM",
+        Command::VFDModeSetting => "VFDModeSetting",
+        Command::DisplayAreaSetting => "DisplayAreaSetting",
+        Command::InternalSpeedSetting => "InternalSpeedSetting",
+        Command::BrightnessSetting => "BrightnessSetting",
+        Command::WriteGRAM => "WriteGRAM",
+        Command::DisplayPosition1Offset => "DisplayPosition1Offset",
+        Command::DisplayPosition2Offset => "DisplayPosition2Offset",
+        Command::DisplayModeSetting => "DisplayModeSetting",
+        Command::FrameSyncSetting => "FrameSyncSetting",
+        Command::OscillationSetting => "OscillationSetting",
+        Command::UnknownInit => "UnknownInit",
+        Command::WakeUp => "WakeUp",
+        Command::Sleep => "Sleep",
+    }
+}
+
+impl spi::ErrorType for Gp1287Model {
+    type Error = ModelError;
+}
+
+impl SpiDevice for Gp1287Model {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ModelError> {
+        let mut parser = Parser::default();
+        // the controller always sees the bits in the order of an MSB first bus
+        let order = self.bit_order;
+        let wire = move |byte: u8| match order {
+            BitOrder::MsbFirst => byte,
+            BitOrder::LsbFirst => byte.reverse_bits(),
+        };
+        let mut len = 0;
+        for operation in operations.iter() {
+            if let Operation::Write(bytes) = operation {
+                self.largest_write = self.largest_write.max(bytes.len());
+                len += bytes.len();
+            }
+        }
+        self.largest_transaction = self.largest_transaction.max(len);
+        let result = operations
+            .iter()
+            .try_for_each(|operation| match operation {
+                Operation::Write(bytes) => bytes
+                    .iter()
+                    .try_for_each(|byte| self.feed(&mut parser, wire(*byte))),
+                Operation::DelayNs(_) => Ok(()),
+                _ => Err(ModelError::UnsupportedOperation),
+            })
+            .and_then(|_| self.finish(&parser));
+        if let Err(e) = result {
+            self.last_error = Some(e);
+        }
+        result
+    }
+}
+
+/// Reset pin which does nothing, for use with the model
+pub struct NoopPin;
+
+impl digital::ErrorType for NoopPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoopPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Delay which returns immediately, for use with the model
+pub struct NoopDelay;
+
+impl DelayNs for NoopDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gp1287bi::VFD256x50;
+    use crate::traits::EEIDisplay;
+
+    #[test]
+    fn init_state() {
+        let mut model = Gp1287Model::new();
+        VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        assert_eq!(model.brightness(), 0x30);
+        assert_eq!(model.vfd_mode(), [0x02, 0x00]);
+        assert_eq!(model.internal_speed(), [0x20, 0x3F, 0x00, 0x01]);
+        assert_eq!(model.position_offset(1), Ok([0x00, 0x04]));
+        assert_eq!(model.position_offset(2), Ok([0x00, 0x3c]));
+        assert_eq!(model.position_offset(0), Err(ModelError::NoSuchPosition(0)));
+        assert_eq!(model.position_offset(3), Err(ModelError::NoSuchPosition(3)));
+        assert_eq!(model.pixel(255, 63), Some(false));
+        assert_eq!(model.pixel(256, 0), None);
+        assert_eq!(model.pixel(0, 64), None);
+        assert!(!model.is_asleep());
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let mut buffer = [0u8; NUM_DISPLAY_BITS as usize];
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = (i * 31) as u8;
+        }
+        vfd.update_frame(&buffer).unwrap();
+        vfd.sleep().unwrap();
+        assert_eq!(model.frame(), buffer);
+        assert!(model.is_asleep());
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let mut model = Gp1287Model::new();
+        let brightness = (Command::BrightnessSetting as u8).reverse_bits();
+        assert_eq!(
+            model.write(&[brightness, 0x01]),
+            Err(ModelError::MissingArguments {
+                command: "BrightnessSetting",
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(model.write(&[0xff]), Err(ModelError::UnknownCommand(0xff)));
+        model
+            .write(&[(Command::Sleep as u8).reverse_bits()])
+            .unwrap();
+        assert_eq!(
+            model.write(&[brightness, 0x00, 0x10]),
+            Err(ModelError::Asleep("BrightnessSetting"))
+        );
+        assert_eq!(
+            model.last_error(),
+            Some(ModelError::Asleep("BrightnessSetting"))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Estimating the current draw of the display
+//!
+//! The supply current of a VFD grows with the brightness and the number of lit pixels.
+//! [PanelPower] models it with three coefficients, measured once per panel type, and is used to
+//! predict the draw of a frame or to find the brightness which keeps a frame within a budget.
+//! There are no datasheet values, measure the panel at a few brightness levels and patterns.
+//!
+//! ```ignore
+//! let panel = PanelPower { base_ua: 90_000, scan_ua: 40_000, pixel_na: 10_000 };
+//! let ma = panel.estimate(&display, 0x200);
+//! vfd.set_power_limit(Some(PowerLimit { panel, budget_ma: 150 }))?;
+//! ```
+
+#[cfg(feature = "graphics")]
+use crate::graphics::Display;
+
+/// Highest value of [EEIDisplay::set_brightness](crate::traits::EEIDisplay::set_brightness)
+pub const MAX_BRIGHTNESS: u32 = 0x3ff;
+
+/// Current coefficients of a panel
+///
+/// The estimate is `base_ua + (scan_ua + lit pixels * pixel_na / 1000) * brightness / MAX_BRIGHTNESS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PanelPower {
+    /// Current drawn regardless of content and brightness (filament and logic), in µA
+    pub base_ua: u32,
+    /// Current of the grid drivers at full brightness, in µA
+    pub scan_ua: u32,
+    /// Additional current of each lit pixel at full brightness, in nA
+    pub pixel_na: u32,
+}
+
+impl PanelPower {
+    /// Estimated current with `lit` pixels on at `brightness`, in µA
+    pub fn estimate_ua(&self, lit: u32, brightness: u32) -> u32 {
+        let brightness = brightness.min(MAX_BRIGHTNESS) as u64;
+        let dynamic = self.dynamic_na(lit) * brightness / (MAX_BRIGHTNESS as u64 * 1000);
+        (self.base_ua as u64 + dynamic).min(u32::MAX as u64) as u32
+    }
+
+    /// Estimated current with `lit` pixels on at `brightness`, in mA rounded up
+    pub fn estimate_ma(&self, lit: u32, brightness: u32) -> u32 {
+        self.estimate_ua(lit, brightness).div_ceil(1000)
+    }
+
+    /// Estimated current of the frame in `display` at `brightness`, in mA rounded up
+    #[cfg(feature = "graphics")]
+    pub fn estimate<D: Display>(&self, display: &D, brightness: u32) -> u32 {
+        self.estimate_ma(lit_pixels(display), brightness)
+    }
+
+    /// Highest brightness keeping `lit` pixels within `budget_ma`
+    ///
+    /// Returns 0 if even a dark panel needs more than the budget.
+    pub fn max_brightness(&self, lit: u32, budget_ma: u32) -> u32 {
+        let available = (budget_ma as u64 * 1000).saturating_sub(self.base_ua as u64);
+        match self.dynamic_na(lit) {
+            0 => MAX_BRIGHTNESS,
+            dynamic => {
+                let brightness = available * 1000 * MAX_BRIGHTNESS as u64 / dynamic;
+                brightness.min(MAX_BRIGHTNESS as u64) as u32
+            }
+        }
+    }
+
+    /// Lowers `brightness` as far as needed to keep `lit` pixels within `budget_ma`
+    pub fn cap_brightness(&self, lit: u32, brightness: u32, budget_ma: u32) -> u32 {
+        brightness.min(self.max_brightness(lit, budget_ma))
+    }
+
+    // content dependent current at full brightness, in nA
+    fn dynamic_na(&self, lit: u32) -> u64 {
+        self.scan_ua as u64 * 1000 + lit as u64 * self.pixel_na as u64
+    }
+}
+
+/// Current budget the driver keeps to, see
+/// [VFD256x50::set_power_limit](crate::gp1287bi::VFD256x50::set_power_limit)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerLimit {
+    /// Coefficients of the panel
+    pub panel: PanelPower,
+    /// Highest estimated current allowed, in mA
+    pub budget_ma: u32,
+}
+
+/// Number of [Color::Green](crate::color::Color::Green) pixels of `display` on the glass
+///
+/// The buffer is counted like the driver counts the GRAM for its power limit, bits of the
+/// unconnected columns of a [Display256x50](crate::gp1287bi::Display256x50) are skipped.
+#[cfg(feature = "graphics")]
+pub fn lit_pixels<D: Display>(display: &D) -> u32 {
+    crate::gp1287bi::glass_lit(display.buffer())
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::gp1287bi::Display256x50;
+    use crate::graphics::DisplayRotation;
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+    use embedded_graphics::Drawable;
+
+    #[test]
+    fn estimates() {
+        let panel = PanelPower {
+            base_ua: 90_000,
+            scan_ua: 40_000,
+            pixel_na: 10_000,
+        };
+        let mut display = Display256x50::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        Rectangle::new(Point::new(10, 5), Size::new(100, 20))
+            .into_styled(PrimitiveStyle::with_fill(Color::Green))
+            .draw(&mut display)
+            .unwrap();
+        // one line of the rectangle lies on an unconnected column
+        assert_eq!(lit_pixels(&display), 1900);
+        assert_eq!(panel.estimate(&display, 0), 90);
+        assert_eq!(panel.estimate(&display, MAX_BRIGHTNESS), 90 + 40 + 19);
+
+        for budget in [50, 100, 120, 200] {
+            let brightness = panel.max_brightness(2000, budget);
+            assert!(brightness == 0 || panel.estimate_ma(2000, brightness) <= budget);
+            if brightness < MAX_BRIGHTNESS {
+                assert!(panel.estimate_ua(2000, brightness + 1) > budget * 1000);
+            }
+        }
+        assert_eq!(panel.max_brightness(2000, 80), 0);
+        assert_eq!(panel.cap_brightness(2000, 0x30, 200), 0x30);
+    }
+}
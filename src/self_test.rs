@@ -0,0 +1,416 @@
+//! Factory self test and dead pixel diagnostic
+//!
+//! [SelfTest] pushes a standard sequence of test patterns to any [EEIDisplay]: all pixels on,
+//! all off, both phases of a checkerboard, a walk over every row and column and a brightness
+//! sweep. After each step a callback decides whether it passed, e.g. after asking the operator
+//! or checking a camera image. A failing row or column step points to a dead grid or anode.
+//!
+//! ```ignore
+//! let mut buffer = [0u8; eei_vfd::buffer_len(56, 256)];
+//! let report = SelfTest::new()
+//!     .dwell(500)
+//!     .run(&mut vfd, &mut buffer, &mut delay, |step| camera.check(step))?;
+//! assert!(report.passed());
+//! ```
+
+use embedded_hal::{delay::DelayNs, digital::*, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::traits::EEIDisplay;
+
+/// Number of failed steps a [SelfTestReport] keeps
+pub const MAX_FAILURES: usize = 16;
+
+/// A single step of the self test
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Every pixel lit
+    AllOn,
+    /// Every pixel dark
+    AllOff,
+    /// Checkerboard of 8x8 pixel squares, `inverted` swaps lit and dark squares
+    Checkerboard {
+        /// Second phase with the squares swapped
+        inverted: bool,
+    },
+    /// Only this buffer row lit
+    Row(u32),
+    /// Only this buffer column lit
+    Column(u32),
+    /// All pixels lit at this brightness
+    Brightness(u32),
+}
+
+/// Outcome of a step, returned by the confirmation callback
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The pattern was shown correctly
+    Pass,
+    /// The pattern was wrong, the test continues
+    Fail,
+    /// Stop the test
+    Abort,
+}
+
+/// Result of [SelfTest::run]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// Number of steps shown
+    pub steps: u32,
+    /// Number of failed steps
+    pub failed: u32,
+    /// True if the callback aborted the test
+    pub aborted: bool,
+    failures: [Option<Step>; MAX_FAILURES],
+}
+
+impl SelfTestReport {
+    /// True if every step was shown and passed
+    pub fn passed(&self) -> bool {
+        self.failed == 0 && !self.aborted
+    }
+
+    /// The first [MAX_FAILURES] failed steps
+    pub fn failures(&self) -> impl Iterator<Item = Step> + '_ {
+        self.failures.iter().flatten().copied()
+    }
+
+    fn record(&mut self, step: Step, verdict: Verdict) {
+        self.steps += 1;
+        match verdict {
+            Verdict::Pass => {}
+            Verdict::Fail => {
+                if let Some(slot) = self.failures.get_mut(self.failed as usize) {
+                    *slot = Some(step);
+                }
+                self.failed += 1;
+            }
+            Verdict::Abort => self.aborted = true,
+        }
+    }
+}
+
+/// Configuration of the self test sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelfTest {
+    dwell_ms: u32,
+    walk_dwell_ms: u32,
+    walks: bool,
+    sweep_steps: u32,
+    max_brightness: u32,
+    brightness_after: u32,
+}
+
+impl Default for SelfTest {
+    fn default() -> Self {
+        SelfTest {
+            dwell_ms: 1000,
+            walk_dwell_ms: 50,
+            walks: true,
+            sweep_steps: 8,
+            max_brightness: 0x3ff,
+            brightness_after: 0x30,
+        }
+    }
+}
+
+impl SelfTest {
+    /// The full sequence with one second per pattern
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how long each full screen pattern and brightness level is shown
+    pub fn dwell(mut self, ms: u32) -> Self {
+        self.dwell_ms = ms;
+        self
+    }
+
+    /// Sets how long each step of the row and column walks is shown
+    pub fn walk_dwell(mut self, ms: u32) -> Self {
+        self.walk_dwell_ms = ms;
+        self
+    }
+
+    /// Enables or disables the row and column walks
+    pub fn walks(mut self, enabled: bool) -> Self {
+        self.walks = enabled;
+        self
+    }
+
+    /// Sets the number of brightness levels from dark to `max`, 0 skips the sweep
+    ///
+    /// A single level shows `max`.
+    pub fn brightness_sweep(mut self, steps: u32, max: u32) -> Self {
+        self.sweep_steps = steps;
+        self.max_brightness = max;
+        self
+    }
+
+    /// Sets the brightness restored after the test
+    pub fn brightness_after(mut self, val: u32) -> Self {
+        self.brightness_after = val;
+        self
+    }
+
+    /// Runs the sequence on `display`
+    ///
+    /// `buffer` is used for the patterns and needs to hold a whole frame, `delay` waits
+    /// the dwell times and `confirm` is called after each step has been shown.
+    /// The display is cleared and the brightness restored afterwards, even after an abort
+    /// or an error.
+    ///
+    /// # Panics
+    ///
+    /// If `buffer` can't hold a frame.
+    pub fn run<SPI, RST, DELAY, D, DL, F>(
+        &self,
+        display: &mut D,
+        buffer: &mut [u8],
+        delay: &mut DL,
+        mut confirm: F,
+    ) -> Result<SelfTestReport, SPI::Error>
+    where
+        SPI: SpiDevice,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        D: EEIDisplay<SPI, RST, DELAY>,
+        DL: DelayNs,
+        F: FnMut(Step) -> Verdict,
+    {
+        let (width, height) = (display.width(), display.height());
+        let len = buffer_len(width as usize, height as usize);
+        assert!(buffer.len() >= len, "buffer too small for a frame");
+        let buffer = &mut buffer[..len];
+
+        let mut report = SelfTestReport::default();
+        let full_screen = [
+            Step::AllOn,
+            Step::AllOff,
+            Step::Checkerboard { inverted: false },
+            Step::Checkerboard { inverted: true },
+        ];
+        let rows = (0..height).map(Step::Row);
+        let columns = (0..width).map(Step::Column);
+        let walks = rows.chain(columns).filter(|_| self.walks);
+        let sweep = (0..self.sweep_steps).map(|i| {
+            let level = match self.sweep_steps {
+                1 => self.max_brightness,
+                steps => (self.max_brightness as u64 * i as u64 / (steps - 1) as u64) as u32,
+            };
+            Step::Brightness(level)
+        });
+
+        let mut last = None;
+        let show = || {
+            for step in full_screen.into_iter().chain(walks).chain(sweep) {
+                let dwell = match step {
+                    Step::Row(_) | Step::Column(_) => self.walk_dwell_ms,
+                    _ => self.dwell_ms,
+                };
+                match step {
+                    Step::Brightness(val) => {
+                        // the pattern stays all on from the first sweep step on
+                        if !matches!(last, Some(Step::Brightness(_))) {
+                            draw(Step::AllOn, buffer, width, height);
+                            display.update_frame(buffer)?;
+                        }
+                        display.set_brightness(val)?;
+                    }
+                    _ => {
+                        draw(step, buffer, width, height);
+                        display.update_frame(buffer)?;
+                    }
+                }
+                delay.delay_ms(dwell);
+                last = Some(step);
+                report.record(step, confirm(step));
+                if report.aborted {
+                    break;
+                }
+            }
+            Ok(())
+        };
+        let shown = show();
+
+        // restored after an error as well, the first error is returned
+        buffer.fill(0);
+        let cleared = display.update_frame(buffer);
+        let restored = display.set_brightness(self.brightness_after);
+        shown.and(cleared).and(restored).map(|_| report)
+    }
+}
+
+// draws the pattern of `step` into a row major buffer
+fn draw(step: Step, buffer: &mut [u8], width: u32, height: u32) {
+    let stride = buffer_len(width as usize, 1);
+    for (y, row) in buffer.chunks_mut(stride).enumerate().take(height as usize) {
+        for (byte_index, byte) in row.iter_mut().enumerate() {
+            *byte = match step {
+                Step::AllOn | Step::Brightness(_) => 0xff,
+                Step::AllOff => 0x00,
+                Step::Checkerboard { inverted } => {
+                    if ((y / 8 + byte_index) % 2 == 0) != inverted {
+                        0xff
+                    } else {
+                        0x00
+                    }
+                }
+                Step::Row(row) => {
+                    if y == row as usize {
+                        0xff
+                    } else {
+                        0x00
+                    }
+                }
+                Step::Column(column) => {
+                    if byte_index == column as usize / 8 {
+                        0x80 >> (column % 8)
+                    } else {
+                        0x00
+                    }
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gp1287bi::VFD256x50;
+    use crate::model::ModelError;
+    use crate::model::{Gp1287Model, NoopDelay, NoopPin};
+    use core::cell::Cell;
+    use embedded_hal::spi::{ErrorType, Operation};
+    use std::vec::Vec;
+
+    #[test]
+    fn patterns() {
+        let mut buffer = [0u8; 2 * 16];
+        draw(Step::Column(9), &mut buffer, 12, 16);
+        assert!(buffer.chunks(2).all(|row| row == [0x00, 0x40]));
+        draw(Step::Row(3), &mut buffer, 12, 16);
+        assert_eq!(buffer.iter().filter(|byte| **byte == 0xff).count(), 2);
+        assert_eq!(&buffer[6..8], &[0xff, 0xff]);
+        draw(Step::Checkerboard { inverted: true }, &mut buffer, 12, 16);
+        assert_eq!(&buffer[..2], &[0x00, 0xff]);
+        assert_eq!(&buffer[16..18], &[0xff, 0x00]);
+    }
+
+    #[test]
+    fn reports_failures() {
+        let mut model = Gp1287Model::new();
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let mut buffer = [0u8; buffer_len(56, 256)];
+        let mut steps = Vec::new();
+        let report = SelfTest::new()
+            .brightness_sweep(3, 0x200)
+            .brightness_after(0x42)
+            .run(&mut vfd, &mut buffer, &mut NoopDelay, |step| {
+                steps.push(step);
+                match step {
+                    Step::Row(7) | Step::Column(50) => Verdict::Fail,
+                    _ => Verdict::Pass,
+                }
+            })
+            .unwrap();
+        assert_eq!(report.steps, 4 + 256 + 56 + 3);
+        assert!(!report.passed());
+        assert_eq!(report.failed, 2);
+        assert!(report.failures().eq([Step::Row(7), Step::Column(50)]));
+        assert_eq!(
+            steps[steps.len() - 3..],
+            [
+                Step::Brightness(0),
+                Step::Brightness(0x100),
+                Step::Brightness(0x200)
+            ]
+        );
+        assert_eq!(model.brightness(), 0x42);
+        assert!(model.frame().iter().all(|byte| *byte == 0));
+
+        let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+        let report = SelfTest::new()
+            .walks(false)
+            .run(&mut vfd, &mut buffer, &mut NoopDelay, |step| {
+                if step == Step::AllOff {
+                    Verdict::Abort
+                } else {
+                    Verdict::Pass
+                }
+            })
+            .unwrap();
+        assert_eq!(report.steps, 2);
+        assert!(report.aborted);
+
+        // a single level is the maximum, large maximums don't overflow
+        for (levels, max, expected) in [(1, 0x200, 0x200), (2, u32::MAX, u32::MAX)] {
+            let mut vfd = VFD256x50::new(&mut model, NoopPin, NoopDelay).unwrap();
+            let mut last = None;
+            SelfTest::new()
+                .walks(false)
+                .brightness_sweep(levels, max)
+                .run(&mut vfd, &mut buffer, &mut NoopDelay, |step| {
+                    last = Some(step);
+                    Verdict::Pass
+                })
+                .unwrap();
+            assert_eq!(last, Some(Step::Brightness(expected)));
+        }
+    }
+
+    #[test]
+    fn restores_after_error() {
+        // fails a single transaction once `countdown` reaches zero
+        struct Glitch<'a> {
+            model: &'a mut Gp1287Model,
+            countdown: &'a Cell<Option<u32>>,
+        }
+        impl ErrorType for Glitch<'_> {
+            type Error = ModelError;
+        }
+        impl SpiDevice for Glitch<'_> {
+            fn transaction(
+                &mut self,
+                operations: &mut [Operation<'_, u8>],
+            ) -> Result<(), ModelError> {
+                match self.countdown.get() {
+                    Some(0) => {
+                        self.countdown.set(None);
+                        Err(ModelError::UnsupportedOperation)
+                    }
+                    n => {
+                        self.countdown.set(n.map(|n| n - 1));
+                        self.model.transaction(operations)
+                    }
+                }
+            }
+        }
+
+        let mut model = Gp1287Model::new();
+        let countdown = Cell::new(None);
+        let spi = Glitch {
+            model: &mut model,
+            countdown: &countdown,
+        };
+        let mut vfd = VFD256x50::new(spi, NoopPin, NoopDelay).unwrap();
+        let mut buffer = [0u8; buffer_len(56, 256)];
+        let mut steps = 0;
+        // the third frame fails
+        countdown.set(Some(5));
+        let result = SelfTest::new().brightness_after(0x42).run(
+            &mut vfd,
+            &mut buffer,
+            &mut NoopDelay,
+            |_| {
+                steps += 1;
+                Verdict::Pass
+            },
+        );
+        assert_eq!(result, Err(ModelError::UnsupportedOperation));
+        assert_eq!(steps, 2);
+        assert_eq!(model.brightness(), 0x42);
+        assert!(model.frame().iter().all(|byte| *byte == 0));
+    }
+}
@@ -0,0 +1,256 @@
+//! Bit banged SPI for boards which connect the display to plain GPIOs
+//!
+//! [SoftSpi] implements [SpiDevice] with three [OutputPin]s and works in [crate::SPI_MODE]:
+//! the clock idles low and the display samples on the rising edge. The display never answers,
+//! so there is no MISO and reads are rejected.
+//!
+//! ```ignore
+//! let spi = SoftSpi::new(clk, mosi, cs, delay)?.frequency(2_000_000);
+//! let mut vfd = VFD256x50::new(spi, rst, delay)?;
+//! ```
+//!
+//! Only available with the `soft-spi` feature.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::spi::{self, ErrorKind, Operation, SpiDevice};
+
+use crate::BitOrder;
+
+/// Errors of [SoftSpi]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SoftSpiError {
+    /// Setting one of the pins failed
+    Pin(digital::ErrorKind),
+    /// There is no MISO, so nothing can be read
+    ReadUnsupported,
+}
+
+impl core::fmt::Display for SoftSpiError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SoftSpiError::Pin(kind) => write!(f, "Setting a pin failed: {}", kind),
+            SoftSpiError::ReadUnsupported => write!(f, "Reading is not supported"),
+        }
+    }
+}
+
+impl spi::Error for SoftSpiError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+fn pin<E: digital::Error>(e: E) -> SoftSpiError {
+    SoftSpiError::Pin(e.kind())
+}
+
+/// SPI device bit banged over GPIOs
+pub struct SoftSpi<CLK, MOSI, CS, DELAY> {
+    clk: CLK,
+    mosi: MOSI,
+    cs: CS,
+    delay: DELAY,
+    half_period_ns: u32,
+    /// from selecting the display to the first clock
+    cs_setup_ns: u32,
+    /// from the last clock to releasing the display
+    cs_hold_ns: u32,
+    bit_order: BitOrder,
+}
+
+impl<CLK, MOSI, CS, DELAY> SoftSpi<CLK, MOSI, CS, DELAY>
+where
+    CLK: OutputPin,
+    MOSI: OutputPin,
+    CS: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Creates a SPI device clocking at up to 1 MHz, MSB first
+    ///
+    /// The actual frequency is lower, depending on how fast the pins can be toggled.
+    /// Releases the display and idles the clock right away, so nothing is selected by a
+    /// floating or low chip select until the first transaction.
+    pub fn new(mut clk: CLK, mosi: MOSI, mut cs: CS, delay: DELAY) -> Result<Self, SoftSpiError> {
+        cs.set_high().map_err(pin)?;
+        clk.set_low().map_err(pin)?;
+        Ok(SoftSpi {
+            clk,
+            mosi,
+            cs,
+            delay,
+            half_period_ns: 500,
+            cs_setup_ns: 500,
+            cs_hold_ns: 500,
+            bit_order: BitOrder::MsbFirst,
+        })
+    }
+
+    /// Sets the maximum clock frequency in Hz
+    pub fn frequency(mut self, hz: u32) -> Self {
+        self.half_period_ns = 500_000_000 / hz.max(1);
+        self
+    }
+
+    /// Sets how long chip select is held low before the first and after the last clock, in ns
+    ///
+    /// Both default to 500 ns, half a period at the default frequency.
+    pub fn cs_delays(mut self, setup_ns: u32, hold_ns: u32) -> Self {
+        self.cs_setup_ns = setup_ns;
+        self.cs_hold_ns = hold_ns;
+        self
+    }
+
+    /// Sets the order in which the bits of each byte are clocked out
+    ///
+    /// The driver needs to know as well, see [VFD256x50::with_bit_order](crate::gp1287bi::VFD256x50::with_bit_order).
+    pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Returns the pins and the delay
+    pub fn release(self) -> (CLK, MOSI, CS, DELAY) {
+        (self.clk, self.mosi, self.cs, self.delay)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), SoftSpiError> {
+        for i in 0..8 {
+            let bit = match self.bit_order {
+                BitOrder::MsbFirst => byte & (0x80 >> i),
+                BitOrder::LsbFirst => byte & (1 << i),
+            };
+            if bit != 0 {
+                self.mosi.set_high().map_err(pin)?;
+            } else {
+                self.mosi.set_low().map_err(pin)?;
+            }
+            self.delay.delay_ns(self.half_period_ns);
+            self.clk.set_high().map_err(pin)?;
+            self.delay.delay_ns(self.half_period_ns);
+            self.clk.set_low().map_err(pin)?;
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), SoftSpiError> {
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    for byte in bytes.iter() {
+                        self.write_byte(*byte)?;
+                    }
+                }
+                Operation::DelayNs(ns) => self.delay.delay_ns(*ns),
+                _ => return Err(SoftSpiError::ReadUnsupported),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<CLK, MOSI, CS, DELAY> spi::ErrorType for SoftSpi<CLK, MOSI, CS, DELAY> {
+    type Error = SoftSpiError;
+}
+
+impl<CLK, MOSI, CS, DELAY> SpiDevice for SoftSpi<CLK, MOSI, CS, DELAY>
+where
+    CLK: OutputPin,
+    MOSI: OutputPin,
+    CS: OutputPin,
+    DELAY: DelayNs,
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), SoftSpiError> {
+        self.clk.set_low().map_err(pin)?;
+        self.cs.set_low().map_err(pin)?;
+        self.delay.delay_ns(self.cs_setup_ns);
+        let result = self.run(operations);
+        self.delay.delay_ns(self.cs_hold_ns);
+        // release the bus even if the transaction failed
+        let deselect = self.cs.set_high().map_err(pin);
+        result.and(deselect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gp1287bi::command::Command;
+    use crate::interface::DisplayInterface;
+    use crate::model::{NoopDelay, NoopPin};
+    use core::cell::RefCell;
+    use embedded_hal_mock::eh1::digital::{Mock, State, Transaction};
+    use std::vec::Vec;
+
+    fn levels(bits: impl IntoIterator<Item = bool>) -> Vec<Transaction> {
+        bits.into_iter()
+            .map(|high| Transaction::set(if high { State::High } else { State::Low }))
+            .collect()
+    }
+
+    #[test]
+    fn clocks_out_reset() {
+        // released in new, the empty write flushing the bus, then the command
+        let cs = Mock::new(&levels([true, false, true, false, true]));
+        let mut clk_levels = levels([false, false, false]);
+        clk_levels.extend(levels((0..8).flat_map(|_| [true, false])));
+        let clk = Mock::new(&clk_levels);
+        // 0b1010_1010 reversed, msb first
+        let mosi = Mock::new(&levels([
+            false, true, false, true, false, true, false, true,
+        ]));
+
+        let mut spi = SoftSpi::new(clk, mosi, cs, NoopDelay).unwrap();
+        let mut interface = DisplayInterface::new(&mut spi, NoopPin, NoopDelay);
+        interface.cmd_with_arg(Command::Reset, &[]).unwrap();
+
+        let (mut clk, mut mosi, mut cs, _) = spi.release();
+        clk.done();
+        mosi.done();
+        cs.done();
+    }
+
+    #[test]
+    fn lsb_first() {
+        // the failed read still selects and releases the display
+        let cs = Mock::new(&levels([true, false, true, false, true]));
+        let mut clk_levels = levels([false, false]);
+        clk_levels.extend(levels((0..8).flat_map(|_| [true, false])));
+        clk_levels.extend(levels([false]));
+        let clk = Mock::new(&clk_levels);
+        let mosi = Mock::new(&levels([
+            true, true, false, false, false, false, false, true,
+        ]));
+
+        let mut spi = SoftSpi::new(clk, mosi, cs, NoopDelay)
+            .unwrap()
+            .bit_order(BitOrder::LsbFirst);
+        spi.write(&[0b1000_0011]).unwrap();
+        assert_eq!(spi.read(&mut [0]), Err(SoftSpiError::ReadUnsupported));
+
+        let (mut clk, mut mosi, mut cs, _) = spi.release();
+        clk.done();
+        mosi.done();
+        cs.done();
+    }
+
+    #[test]
+    fn cs_delays() {
+        struct Log<'a>(&'a RefCell<Vec<u32>>);
+        impl DelayNs for Log<'_> {
+            fn delay_ns(&mut self, ns: u32) {
+                self.0.borrow_mut().push(ns);
+            }
+        }
+
+        let log = RefCell::new(Vec::new());
+        let mut spi = SoftSpi::new(NoopPin, NoopPin, NoopPin, Log(&log))
+            .unwrap()
+            .cs_delays(100, 200);
+        spi.write(&[0x5a]).unwrap();
+        let log = log.borrow();
+        assert_eq!(log.len(), 2 + 16);
+        assert_eq!((log[0], log[17]), (100, 200));
+        assert!(log[1..17].iter().all(|ns| *ns == 500));
+    }
+}
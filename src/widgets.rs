@@ -0,0 +1,61 @@
+//! Ready made widgets drawing onto any [Display](crate::graphics::Display)
+//!
+//! All widgets are laid out relative to a bounding box and never allocate.
+//! [Widget::draw] returns the region which was changed, so only that part needs to be flushed.
+
+mod bar;
+mod icons;
+mod marquee;
+mod sparkline;
+
+pub use self::bar::{LevelMeter, Orientation, ProgressBar};
+pub use self::icons::{BatteryIcon, WifiIcon};
+pub use self::marquee::{Marquee, MarqueeMode};
+pub use self::sparkline::Sparkline;
+
+use crate::color::Color;
+use crate::graphics::Display;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Something that can be drawn onto a [Display]
+pub trait Widget {
+    /// Draws the widget and returns the region it changed
+    fn draw<D: Display>(&self, target: &mut D) -> Result<Rectangle, D::Error>;
+}
+
+// draws the one pixel wide outline of `area`
+fn outline<D: Display>(target: &mut D, area: &Rectangle, color: Color) -> Result<(), D::Error> {
+    let Size { width, height } = area.size;
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+    let (left, top) = (area.top_left.x, area.top_left.y);
+    let (right, bottom) = (left + width as i32 - 1, top + height as i32 - 1);
+    let horizontal = (left..=right).flat_map(|x| [Point::new(x, top), Point::new(x, bottom)]);
+    let vertical = (top..=bottom).flat_map(|y| [Point::new(left, y), Point::new(right, y)]);
+    target.draw_iter(horizontal.chain(vertical).map(|p| Pixel(p, color)))
+}
+
+// draws a bitmap with one u16 per row, the msb of the lowest `width` bits is the left most pixel
+fn bitmap<D: Display>(
+    target: &mut D,
+    origin: Point,
+    width: u32,
+    rows: &[u16],
+    lit: impl Fn(usize) -> bool,
+) -> Result<(), D::Error> {
+    let pixels = rows.iter().enumerate().flat_map(|(y, row)| {
+        let lit = lit(y);
+        (0..width).map(move |x| {
+            let set = row & (1 << (width - 1 - x)) != 0;
+            let color = if set && lit {
+                Color::Green
+            } else {
+                Color::Dark
+            };
+            Pixel(origin + Point::new(x as i32, y as i32), color)
+        })
+    });
+    target.draw_iter(pixels)
+}
@@ -0,0 +1,238 @@
+//! Progress bars and level meters
+
+use super::{outline, Widget};
+use crate::color::Color;
+use crate::graphics::Display;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Direction in which a bar fills up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Orientation {
+    /// Fills from left to right
+    #[default]
+    Horizontal,
+    /// Fills from bottom to top
+    Vertical,
+}
+
+/// A bar filled in proportion to `value / max`
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressBar {
+    area: Rectangle,
+    value: u32,
+    max: u32,
+    orientation: Orientation,
+    border: bool,
+}
+
+impl ProgressBar {
+    /// Creates an empty, bordered, horizontal bar filling `area`
+    pub fn new(area: Rectangle, max: u32) -> Self {
+        ProgressBar {
+            area,
+            value: 0,
+            max,
+            orientation: Orientation::default(),
+            border: true,
+        }
+    }
+
+    /// Sets the direction the bar fills up
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Enables or disables the border (with one pixel padding) around the bar
+    pub fn border(mut self, border: bool) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Sets the current value, clamped to `max`
+    pub fn set_value(&mut self, value: u32) {
+        self.value = value.min(self.max);
+    }
+
+    /// Returns the current value
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+}
+
+impl Widget for ProgressBar {
+    fn draw<D: Display>(&self, target: &mut D) -> Result<Rectangle, D::Error> {
+        let inner = if self.border {
+            outline(target, &self.area, Color::Green)?;
+            target.fill_solid(&self.area.offset(-1), Color::Dark)?;
+            self.area.offset(-2)
+        } else {
+            self.area
+        };
+        let (lit, dark) = split(&inner, self.value, self.max, self.orientation);
+        target.fill_solid(&lit, Color::Green)?;
+        target.fill_solid(&dark, Color::Dark)?;
+        Ok(self.area)
+    }
+}
+
+/// A bar made of `segments` separate blocks, e.g. for audio levels or signal strength
+#[derive(Clone, Copy, Debug)]
+pub struct LevelMeter {
+    area: Rectangle,
+    level: u32,
+    max: u32,
+    segments: u32,
+    orientation: Orientation,
+}
+
+impl LevelMeter {
+    /// Creates an empty horizontal meter with `segments` blocks filling `area`
+    pub fn new(area: Rectangle, max: u32, segments: u32) -> Self {
+        LevelMeter {
+            area,
+            level: 0,
+            max,
+            segments: segments.max(1),
+            orientation: Orientation::default(),
+        }
+    }
+
+    /// Sets the direction the meter fills up
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Sets the current level, clamped to `max`
+    pub fn set_level(&mut self, level: u32) {
+        self.level = level.min(self.max);
+    }
+
+    /// Returns the current level
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+}
+
+impl Widget for LevelMeter {
+    fn draw<D: Display>(&self, target: &mut D) -> Result<Rectangle, D::Error> {
+        target.fill_solid(&self.area, Color::Dark)?;
+        // in u64, level and segments can be anything up to u32::MAX
+        let lit = (self.level as u64 * self.segments as u64 + self.max as u64 / 2)
+            .checked_div(self.max as u64)
+            .unwrap_or(0) as u32;
+        let length = match self.orientation {
+            Orientation::Horizontal => self.area.size.width,
+            Orientation::Vertical => self.area.size.height,
+        };
+        // one pixel gap between the blocks
+        let block = length.saturating_add(1) / self.segments;
+        for segment in 0..lit {
+            let start = (segment * block) as i32;
+            let size = block.saturating_sub(1);
+            let rect = match self.orientation {
+                Orientation::Horizontal => Rectangle::new(
+                    self.area.top_left + Point::new(start, 0),
+                    Size::new(size, self.area.size.height),
+                ),
+                Orientation::Vertical => Rectangle::new(
+                    self.area.top_left
+                        + Point::new(0, self.area.size.height as i32 - start - size as i32),
+                    Size::new(self.area.size.width, size),
+                ),
+            };
+            target.fill_solid(&rect, Color::Green)?;
+        }
+        Ok(self.area)
+    }
+}
+
+// splits `area` into the filled and the empty part
+fn split(
+    area: &Rectangle,
+    value: u32,
+    max: u32,
+    orientation: Orientation,
+) -> (Rectangle, Rectangle) {
+    let Size { width, height } = area.size;
+    let fill = |length: u32| {
+        (length as u64 * value as u64)
+            .checked_div(max as u64)
+            .unwrap_or(0) as u32
+    };
+    match orientation {
+        Orientation::Horizontal => {
+            let lit = fill(width);
+            (
+                Rectangle::new(area.top_left, Size::new(lit, height)),
+                Rectangle::new(
+                    area.top_left + Point::new(lit as i32, 0),
+                    Size::new(width - lit, height),
+                ),
+            )
+        }
+        Orientation::Vertical => {
+            let lit = fill(height);
+            (
+                Rectangle::new(
+                    area.top_left + Point::new(0, (height - lit) as i32),
+                    Size::new(width, lit),
+                ),
+                Rectangle::new(area.top_left, Size::new(width, height - lit)),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_display_eq;
+    use crate::graphics::VarDisplay;
+
+    #[test]
+    fn progress_bar() {
+        let mut buffer = [0u8; 2 * 6];
+        let mut display = VarDisplay::new(16, 6, &mut buffer);
+        let mut bar = ProgressBar::new(Rectangle::new(Point::new(1, 0), Size::new(14, 6)), 100);
+        bar.set_value(50);
+        let changed = bar.draw(&mut display).unwrap();
+        assert_eq!(changed, Rectangle::new(Point::new(1, 0), Size::new(14, 6)));
+        assert_display_eq!(display, include_str!("golden/progress_bar.pbm"));
+    }
+
+    #[test]
+    fn level_meter() {
+        let mut buffer = [0u8; 2 * 8];
+        let mut display = VarDisplay::new(16, 8, &mut buffer);
+        let mut meter = LevelMeter::new(Rectangle::new(Point::zero(), Size::new(3, 8)), 4, 4)
+            .orientation(Orientation::Vertical);
+        meter.set_level(3);
+        meter.draw(&mut display).unwrap();
+        let mut meter = LevelMeter::new(Rectangle::new(Point::new(4, 2), Size::new(11, 3)), 10, 3);
+        meter.set_level(7);
+        meter.draw(&mut display).unwrap();
+        assert_display_eq!(display, include_str!("golden/level_meter.pbm"));
+    }
+
+    #[test]
+    fn large_values() {
+        let area = Rectangle::new(Point::zero(), Size::new(10, 1));
+        let horizontal = Orientation::Horizontal;
+        let (lit, dark) = split(&area, u32::MAX - 1, u32::MAX, horizontal);
+        assert_eq!((lit.size.width, dark.size.width), (9, 1));
+        let (lit, _) = split(&area, u32::MAX, u32::MAX, horizontal);
+        assert_eq!(lit.size.width, 10);
+
+        let mut buffer = [0u8; 2];
+        let mut display = VarDisplay::new(16, 1, &mut buffer);
+        let area = Rectangle::new(Point::zero(), Size::new(16, 1));
+        let mut meter = LevelMeter::new(area, u32::MAX, 4);
+        meter.set_level(u32::MAX);
+        meter.draw(&mut display).unwrap();
+        // four blocks of three pixels
+        assert_eq!(buffer, [0b1110_1110, 0b1110_1110]);
+    }
+}
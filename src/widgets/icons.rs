@@ -0,0 +1,119 @@
+//! Status icons
+
+use super::{bitmap, Widget};
+use crate::color::Color;
+use crate::graphics::Display;
+use embedded_graphics_core::geometry::AnchorPoint;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+#[rustfmt::skip]
+const BATTERY: [u16; 7] = [
+    0b1111_1111_1100,
+    0b1000_0000_0100,
+    0b1000_0000_0111,
+    0b1000_0000_0111,
+    0b1000_0000_0111,
+    0b1000_0000_0100,
+    0b1111_1111_1100,
+];
+const BATTERY_SIZE: Size = Size::new(12, 7);
+
+#[rustfmt::skip]
+const WIFI: [u16; 7] = [
+    0b0_1111_1110,
+    0b1_0000_0001,
+    0b0_0111_1100,
+    0b0_1000_0010,
+    0b0_0011_1000,
+    0b0_0100_0100,
+    0b0_0001_0000,
+];
+const WIFI_SIZE: Size = Size::new(9, 7);
+
+/// A 12x7 battery, centered in its area
+#[derive(Clone, Copy, Debug)]
+pub struct BatteryIcon {
+    area: Rectangle,
+    level: u8,
+}
+
+impl BatteryIcon {
+    /// Creates an empty battery icon
+    pub fn new(area: Rectangle) -> Self {
+        BatteryIcon { area, level: 0 }
+    }
+
+    /// Sets the charge in percent, clamped to 100
+    pub fn set_level(&mut self, percent: u8) {
+        self.level = percent.min(100);
+    }
+}
+
+impl Widget for BatteryIcon {
+    fn draw<D: Display>(&self, target: &mut D) -> Result<Rectangle, D::Error> {
+        let icon = self.area.resized(BATTERY_SIZE, AnchorPoint::Center);
+        bitmap(target, icon.top_left, BATTERY_SIZE.width, &BATTERY, |_| {
+            true
+        })?;
+        // the inside has room for six columns, keeping one pixel distance to the shell
+        let fill = (self.level as u32 * 6 + 50) / 100;
+        target.fill_solid(
+            &Rectangle::new(icon.top_left + Point::new(2, 2), Size::new(fill, 3)),
+            Color::Green,
+        )?;
+        Ok(icon)
+    }
+}
+
+/// A 9x7 wifi symbol with up to three arcs, centered in its area
+#[derive(Clone, Copy, Debug)]
+pub struct WifiIcon {
+    area: Rectangle,
+    strength: u8,
+}
+
+impl WifiIcon {
+    /// Creates a wifi icon without any arcs
+    pub fn new(area: Rectangle) -> Self {
+        WifiIcon { area, strength: 0 }
+    }
+
+    /// Sets the number of lit arcs, clamped to 3
+    pub fn set_strength(&mut self, arcs: u8) {
+        self.strength = arcs.min(3);
+    }
+}
+
+impl Widget for WifiIcon {
+    fn draw<D: Display>(&self, target: &mut D) -> Result<Rectangle, D::Error> {
+        let icon = self.area.resized(WIFI_SIZE, AnchorPoint::Center);
+        // two rows per arc, outermost first, the dot in the last row is always lit
+        let lit = |row: usize| row == WIFI.len() - 1 || 3 - row / 2 <= self.strength as usize;
+        bitmap(target, icon.top_left, WIFI_SIZE.width, &WIFI, lit)?;
+        Ok(icon)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_display_eq;
+    use crate::graphics::VarDisplay;
+
+    #[test]
+    fn icons() {
+        let mut buffer = [0u8; 3 * 9];
+        let mut display = VarDisplay::new(24, 9, &mut buffer);
+        let mut battery = BatteryIcon::new(Rectangle::new(Point::zero(), Size::new(12, 9)));
+        battery.set_level(50);
+        assert_eq!(
+            battery.draw(&mut display).unwrap(),
+            Rectangle::new(Point::new(0, 1), BATTERY_SIZE)
+        );
+        let mut wifi = WifiIcon::new(Rectangle::new(Point::new(12, 0), Size::new(12, 9)));
+        wifi.set_strength(2);
+        wifi.draw(&mut display).unwrap();
+        assert_display_eq!(display, include_str!("golden/icons.pbm"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Smoothly scrolling text
+
+use super::Widget;
+use crate::color::Color;
+use crate::graphics::{rotated_size, Display};
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// What happens when the content scrolled out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MarqueeMode {
+    /// Content leaves on the left and comes back in after the gap
+    #[default]
+    Wrap,
+    /// Content scrolls until its end is visible, then scrolls back
+    Bounce,
+}
+
+/// A ticker scrolling pre-rendered content through a rectangle, one pixel per tick
+///
+/// The content is an off-screen strip, usually a [VarDisplay](crate::graphics::VarDisplay)
+/// with the text drawn onto it once, so ticking never renders fonts again.
+///
+/// The GP1287 only offers offsets for the whole glass, so scrolling is done in software
+/// by redrawing the rectangle on each tick.
+pub struct Marquee<'a, S> {
+    strip: &'a S,
+    area: Rectangle,
+    mode: MarqueeMode,
+    gap: u32,
+    pause: u32,
+    background: Color,
+    offset: u32,
+    backwards: bool,
+    hold: u32,
+}
+
+impl<'a, S: Display> Marquee<'a, S> {
+    /// Creates a marquee showing `strip` inside `area`
+    pub fn new(strip: &'a S, area: Rectangle) -> Self {
+        Marquee {
+            strip,
+            area,
+            mode: MarqueeMode::default(),
+            gap: area.size.width / 2,
+            pause: 0,
+            background: Color::Dark,
+            offset: 0,
+            backwards: false,
+            hold: 0,
+        }
+    }
+
+    /// Sets how the marquee behaves at the end of the content
+    pub fn mode(mut self, mode: MarqueeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the empty space between the end and the next start of wrapping content in pixels
+    pub fn gap(mut self, gap: u32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Sets the number of ticks the content rests at its start (and end when bouncing)
+    pub fn pause(mut self, ticks: u32) -> Self {
+        self.pause = ticks;
+        self.hold = ticks;
+        self
+    }
+
+    /// Sets the color of the gap and everything not covered by the content
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = color;
+        self
+    }
+
+    /// Scrolls back to the start
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.backwards = false;
+        self.hold = self.pause;
+    }
+
+    /// Current scroll position in pixels
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Advances the content by one pixel
+    pub fn tick(&mut self) {
+        if self.hold > 0 {
+            self.hold -= 1;
+            return;
+        }
+        let width = rotated_size(self.strip).width;
+        match self.mode {
+            MarqueeMode::Wrap => {
+                self.offset = (self.offset + 1) % (width + self.gap).max(1);
+                if self.offset == 0 {
+                    self.hold = self.pause;
+                }
+            }
+            MarqueeMode::Bounce => {
+                let end = width.saturating_sub(self.area.size.width);
+                if end == 0 {
+                    return;
+                }
+                if self.backwards {
+                    self.offset -= 1;
+                } else {
+                    self.offset += 1;
+                }
+                if self.offset == 0 || self.offset == end {
+                    self.backwards = self.offset == end;
+                    self.hold = self.pause;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, S: Display> Widget for Marquee<'a, S> {
+    /// Draws the visible part of the content into the rectangle of `target`
+    fn draw<D: Display>(&self, target: &mut D) -> Result<Rectangle, D::Error> {
+        let width = rotated_size(self.strip).width;
+        let period = width + self.gap;
+        let Size {
+            width: area_width,
+            height: area_height,
+        } = self.area.size;
+        let pixels = (0..area_height).flat_map(move |y| {
+            (0..area_width).map(move |x| {
+                let column = match self.mode {
+                    MarqueeMode::Wrap => (self.offset + x) % period.max(1),
+                    MarqueeMode::Bounce => self.offset + x,
+                };
+                let color = self
+                    .strip
+                    .get_pixel(Point::new(column as i32, y as i32))
+                    .unwrap_or(self.background);
+                Pixel(self.area.top_left + Point::new(x as i32, y as i32), color)
+            })
+        });
+        target.draw_iter(pixels)?;
+        Ok(self.area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{DisplayRotation, VarDisplay};
+
+    #[test]
+    fn wraps_with_gap_and_pause() {
+        let mut buffer = [0u8; 1];
+        let strip = VarDisplay::new(4, 1, &mut buffer);
+        let mut marquee = Marquee::new(&strip, Rectangle::new(Point::zero(), Size::new(2, 1)))
+            .gap(2)
+            .pause(1);
+        let mut offsets = [0u32; 9];
+        for offset in offsets.iter_mut() {
+            *offset = marquee.offset();
+            marquee.tick();
+        }
+        assert_eq!(offsets, [0, 0, 1, 2, 3, 4, 5, 0, 0]);
+    }
+
+    #[test]
+    fn scrolls_rotated_strip() {
+        let mut buffer = [0u8; 4];
+        let mut strip = VarDisplay::new(1, 4, &mut buffer);
+        strip.set_rotation(DisplayRotation::Rotate90);
+        let mut marquee = Marquee::new(&strip, Rectangle::new(Point::zero(), Size::new(2, 1)))
+            .gap(2)
+            .pause(1);
+        let mut offsets = [0u32; 9];
+        for offset in offsets.iter_mut() {
+            *offset = marquee.offset();
+            marquee.tick();
+        }
+        assert_eq!(offsets, [0, 0, 1, 2, 3, 4, 5, 0, 0]);
+    }
+
+    #[test]
+    fn bounces() {
+        let mut buffer = [0u8; 1];
+        let strip = VarDisplay::new(5, 1, &mut buffer);
+        let mut marquee = Marquee::new(&strip, Rectangle::new(Point::zero(), Size::new(3, 1)))
+            .mode(MarqueeMode::Bounce);
+        let mut offsets = [0u32; 6];
+        for offset in offsets.iter_mut() {
+            *offset = marquee.offset();
+            marquee.tick();
+        }
+        assert_eq!(offsets, [0, 1, 2, 1, 0, 1]);
+    }
+
+    #[test]
+    fn draws_scrolled_content() {
+        let mut strip_buffer = [0b1000_0000u8];
+        let strip = VarDisplay::new(8, 1, &mut strip_buffer);
+        let mut marquee =
+            Marquee::new(&strip, Rectangle::new(Point::new(8, 0), Size::new(8, 1))).gap(0);
+        marquee.tick();
+        marquee.tick();
+
+        let mut buffer = [0u8; 2];
+        let mut display = VarDisplay::new(16, 1, &mut buffer);
+        marquee.draw(&mut display).unwrap();
+        // the lit first column wrapped around to the end of the area
+        assert_eq!(display.buffer(), &[0x00, 0b0000_0010]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Small line graphs
+
+use super::Widget;
+use crate::color::Color;
+use crate::graphics::Display;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// A line graph of `values`, stretched to fill its area
+///
+/// The vertical range defaults to the minimum and maximum of the values.
+#[derive(Clone, Copy, Debug)]
+pub struct Sparkline<'a> {
+    area: Rectangle,
+    values: &'a [i32],
+    range: Option<(i32, i32)>,
+}
+
+impl<'a> Sparkline<'a> {
+    /// Creates a graph of `values` inside `area`
+    pub fn new(area: Rectangle, values: &'a [i32]) -> Self {
+        Sparkline {
+            area,
+            values,
+            range: None,
+        }
+    }
+
+    /// Uses a fixed vertical range instead of the minimum and maximum of the values
+    ///
+    /// Reversed bounds are swapped.
+    pub fn range(mut self, min: i32, max: i32) -> Self {
+        self.range = Some((min.min(max), min.max(max)));
+        self
+    }
+
+    // position of sample `index` inside the area
+    fn point(&self, index: usize, min: i32, max: i32) -> Point {
+        let Size { width, height } = self.area.size;
+        let x = match self.values.len() {
+            0 | 1 => 0,
+            n => index as i64 * (width as i64 - 1) / (n as i64 - 1),
+        };
+        let value = self.values[index].clamp(min, max) as i64;
+        let span = (max as i64 - min as i64).max(1);
+        let y = (height as i64 - 1) - (value - min as i64) * (height as i64 - 1) / span;
+        self.area.top_left + Point::new(x as i32, y as i32)
+    }
+}
+
+impl<'a> Widget for Sparkline<'a> {
+    fn draw<D: Display>(&self, target: &mut D) -> Result<Rectangle, D::Error> {
+        target.fill_solid(&self.area, Color::Dark)?;
+        let (min, max) = self.range.unwrap_or_else(|| {
+            let min = self.values.iter().copied().min().unwrap_or(0);
+            let max = self.values.iter().copied().max().unwrap_or(0);
+            (min, max)
+        });
+        if !self.values.is_empty() {
+            target.draw_iter(core::iter::once(Pixel(
+                self.point(0, min, max),
+                Color::Green,
+            )))?;
+        }
+        for index in 1..self.values.len() {
+            let (from, to) = (self.point(index - 1, min, max), self.point(index, min, max));
+            target.draw_iter(Line::new(from, to).map(|p| Pixel(p, Color::Green)))?;
+        }
+        Ok(self.area)
+    }
+}
+
+// Bresenham line between two points, both ends included
+struct Line {
+    current: Point,
+    end: Point,
+    delta: Point,
+    step: Point,
+    error: i32,
+    done: bool,
+}
+
+impl Line {
+    fn new(start: Point, end: Point) -> Self {
+        let delta = Point::new((end.x - start.x).abs(), -(end.y - start.y).abs());
+        let step = Point::new((end.x - start.x).signum(), (end.y - start.y).signum());
+        Line {
+            current: start,
+            end,
+            delta,
+            step,
+            error: delta.x + delta.y,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Line {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        if self.done {
+            return None;
+        }
+        let point = self.current;
+        if point == self.end {
+            self.done = true;
+            return Some(point);
+        }
+        let double = 2 * self.error;
+        if double >= self.delta.y {
+            self.error += self.delta.y;
+            self.current.x += self.step.x;
+        }
+        if double <= self.delta.x {
+            self.error += self.delta.x;
+            self.current.y += self.step.y;
+        }
+        Some(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_display_eq;
+    use crate::graphics::VarDisplay;
+
+    #[test]
+    fn sparkline() {
+        let mut buffer = [0u8; 2 * 5];
+        let mut display = VarDisplay::new(16, 5, &mut buffer);
+        let values = [0, 4, 2, 2, 8, 0];
+        Sparkline::new(Rectangle::new(Point::new(1, 0), Size::new(11, 5)), &values)
+            .draw(&mut display)
+            .unwrap();
+        assert_display_eq!(display, include_str!("golden/sparkline.pbm"));
+
+        // a reversed range draws like the ordered one
+        let area = Rectangle::new(Point::new(1, 0), Size::new(11, 5));
+        let mut reversed = [0u8; 2 * 5];
+        let mut display = VarDisplay::new(16, 5, &mut reversed);
+        Sparkline::new(area, &values)
+            .range(8, 0)
+            .draw(&mut display)
+            .unwrap();
+        assert_display_eq!(display, include_str!("golden/sparkline.pbm"));
+    }
+}